@@ -0,0 +1,283 @@
+//! Anchors of headings, which are generated from the text, so formatting the
+//! text may change them, e.g. `## 使用Vue` -> `## 使用 Vue` changes `#使用vue`
+//! into `#使用-vue`, and the links to it are broken.
+//!
+//! - the anchors before and after formatting are compared heading by heading
+//! - the fragment links like `[见此](#使用vue)` and the definitions like
+//!   `[见此]: #使用vue` in the document are updated, or reported if
+//!   `anchors.links` is `report`
+//! - the links from other documents like `[见此](guide.md#使用vue)` are handled
+//!   by [`update_fragment_links`], after all the documents are formatted
+
+use std::{borrow::Cow, collections::HashMap, ops::Range};
+
+use pulldown_cmark::{Event, LinkType, Parser, Tag};
+
+use crate::{
+    config::{AnchorLinks, Config, Slugger},
+    diagnostic::Diagnostic,
+    front_matter::cut_front_matter,
+    link::{definition_url, get_definitions},
+};
+
+/// ASCII punctuations replaced by `-` in VuePress, with the curly quotes.
+const VUEPRESS_SPECIAL: &str = "~`!@#$%^&*()-_+=[]{}|\\;:\"'“”‘’<>,.?/";
+
+/// An anchor of a heading changed by formatting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnchorChange {
+    pub old: String,
+    pub new: String,
+}
+
+impl Slugger {
+    /// The anchor of a heading, without the suffix of the duplicated ones.
+    pub fn slug(&self, text: &str) -> String {
+        match self {
+            Slugger::GitHub => github_slug(text),
+            Slugger::VuePress => vuepress_slug(text),
+        }
+    }
+}
+
+/// Like `github-slugger`, lowercased, the punctuations are removed and the
+/// spaces are replaced with `-`.
+fn github_slug(text: &str) -> String {
+    text.to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Like `slugify` of VuePress, the runs of spaces and punctuations are
+/// replaced with one `-`, and a leading digit is prefixed with `_`. Full-width
+/// forms are taken as ASCII, as the NFKD normalization does.
+fn vuepress_slug(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    let mut dash = false;
+    for c in text.chars() {
+        let c = match c {
+            '\u{3000}' => ' ',
+            '\u{ff01}'..='\u{ff5e}' => char::from_u32(c as u32 - 0xfee0).unwrap(),
+            c => c,
+        };
+        if c.is_control() || ('\u{300}'..='\u{36f}').contains(&c) {
+            continue;
+        }
+        if c.is_whitespace() || VUEPRESS_SPECIAL.contains(c) {
+            dash = true;
+            continue;
+        }
+        if dash && !res.is_empty() {
+            res.push('-');
+        }
+        dash = false;
+        res.push(c);
+    }
+    if res.starts_with(|c: char| c.is_ascii_digit()) {
+        res.insert(0, '_');
+    }
+    res.to_lowercase()
+}
+
+/// The anchors of the headings in order, the duplicated ones are suffixed
+/// with `-1`, `-2` and so on.
+pub(crate) fn get_anchors<'a, 'b: 'a>(
+    events: impl IntoIterator<Item = &'a Event<'b>>,
+    slugger: Slugger,
+) -> Vec<String> {
+    let mut res = Vec::new();
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    let mut heading: Option<String> = None;
+    for event in events {
+        match event {
+            Event::Start(Tag::Heading(..)) => heading = Some(String::new()),
+            Event::End(Tag::Heading(..)) => {
+                let slug = slugger.slug(&heading.take().unwrap_or_default());
+                let count = counts.entry(slug.clone()).or_default();
+                res.push(match *count {
+                    0 => slug,
+                    n => format!("{slug}-{n}"),
+                });
+                *count += 1;
+            }
+            Event::Text(s) | Event::Code(s) => {
+                if let Some(heading) = &mut heading {
+                    heading.push_str(s);
+                }
+            }
+            _ => (),
+        }
+    }
+    res
+}
+
+/// Compare the anchors before and after formatting.
+pub(crate) fn get_anchor_changes(old: Vec<String>, new: Vec<String>) -> Vec<AnchorChange> {
+    old.into_iter()
+        .zip(new)
+        .filter(|(old, new)| old != new)
+        .map(|(old, new)| AnchorChange { old, new })
+        .collect()
+}
+
+/// The fragments of the links in the document with the offsets, e.g.
+/// `[见此](#标题)` and `[见此]: #标题`.
+pub(crate) fn get_fragment_links(events: &[(Event, Range<usize>)]) -> Vec<(String, usize)> {
+    events
+        .iter()
+        .filter_map(|(event, range)| match event {
+            Event::Start(Tag::Link(LinkType::Inline, url, _)) => {
+                url.strip_prefix('#').map(|x| (x.to_string(), range.start))
+            }
+            Event::Html(s) => {
+                let url = definition_url(s)?;
+                s[url.clone()]
+                    .strip_prefix('#')
+                    .map(|x| (x.to_string(), range.start + url.start))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Update the fragment links to the changed anchors in the events.
+pub(crate) fn update_anchor_links(events: &mut [Event], changes: &[AnchorChange]) {
+    for event in events {
+        match event {
+            Event::Start(Tag::Link(_, url, _)) | Event::End(Tag::Link(_, url, _)) => {
+                if let Some(change) = url.strip_prefix('#').and_then(|x| find_change(x, changes)) {
+                    *url = format!("#{}", change.new).into();
+                }
+            }
+            Event::Html(s) => {
+                let Some(url) = definition_url(s) else {
+                    continue;
+                };
+                if let Some(change) = s[url.clone()]
+                    .strip_prefix('#')
+                    .and_then(|x| find_change(x, changes))
+                {
+                    let mut source = s.to_string();
+                    source.replace_range(url, &format!("#{}", change.new));
+                    *s = source.into();
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Report the fragment links to the changed anchors.
+pub(crate) fn report_anchor_links(
+    links: &[(String, usize)],
+    changes: &[AnchorChange],
+    offset: usize,
+) -> Vec<Diagnostic> {
+    links
+        .iter()
+        .filter_map(|(fragment, start)| {
+            find_change(fragment, changes).map(|change| broken_link(change, offset + start))
+        })
+        .collect()
+}
+
+/// Update the links to the anchors changed in other documents, e.g.
+/// `[见此](guide.md#使用vue)`, or report them if `anchors.links` is `report`.
+/// `resolve` gets the changes of the linked document by the path in the URL.
+pub fn update_fragment_links<'c>(
+    text: &str,
+    config: &Config,
+    resolve: impl Fn(&str) -> Option<&'c [AnchorChange]>,
+) -> (String, Vec<Diagnostic>) {
+    let Some(anchors) = &config.anchors else {
+        return (text.to_string(), Vec::new());
+    };
+    let (front_matter, body) = cut_front_matter(text);
+    let options = config.extensions.to_options();
+
+    let mut urls: Vec<Range<usize>> = Parser::new_ext(body, options)
+        .into_offset_iter()
+        .filter_map(|(event, range)| match event {
+            Event::Start(Tag::Link(LinkType::Inline, url, _)) => body[range.clone()]
+                .rfind(url.as_ref())
+                .map(|x| range.start + x..range.start + x + url.len()),
+            _ => None,
+        })
+        .collect();
+    urls.extend(get_definitions(body, options).iter().filter_map(|x| {
+        definition_url(&body[x.span.clone()])
+            .map(|url| x.span.start + url.start..x.span.start + url.end)
+    }));
+    urls.sort_by_key(|x| x.start);
+
+    let mut res = body.to_string();
+    let mut diagnostics = Vec::new();
+    for url in urls.into_iter().rev() {
+        let Some((path, fragment)) = body[url.clone()].split_once('#') else {
+            continue;
+        };
+        if path.is_empty() || path.contains(':') {
+            continue;
+        }
+        let Some(change) = resolve(&percent_decode(path)).and_then(|x| find_change(fragment, x))
+        else {
+            continue;
+        };
+        match anchors.links {
+            AnchorLinks::Update => {
+                res.replace_range(url, &format!("{path}#{}", change.new));
+            }
+            AnchorLinks::Report => {
+                diagnostics.push(broken_link(change, front_matter.len() + url.start));
+            }
+        }
+    }
+    diagnostics.reverse();
+    (format!("{front_matter}{res}"), diagnostics)
+}
+
+fn find_change<'c>(fragment: &str, changes: &'c [AnchorChange]) -> Option<&'c AnchorChange> {
+    let fragment = percent_decode(fragment);
+    changes.iter().find(|x| x.old == fragment)
+}
+
+fn broken_link(change: &AnchorChange, offset: usize) -> Diagnostic {
+    Diagnostic::new(
+        offset,
+        format!(
+            "the anchor `#{}` is changed into `#{}`, the link is broken",
+            change.old, change.new
+        ),
+    )
+}
+
+/// Decode the percent-encoded bytes, e.g. `%E4%BD%BF%E7%94%A8` -> `使用`.
+fn percent_decode(s: &str) -> Cow<'_, str> {
+    if !s.contains('%') {
+        return Cow::Borrowed(s);
+    }
+    let mut res = Vec::with_capacity(s.len());
+    let mut i = 0;
+    while i < s.len() {
+        let byte = s
+            .get(i + 1..i + 3)
+            .filter(|x| s.as_bytes()[i] == b'%' && x.bytes().all(|b| b.is_ascii_hexdigit()))
+            .and_then(|x| u8::from_str_radix(x, 16).ok());
+        match byte {
+            Some(byte) => {
+                res.push(byte);
+                i += 3;
+            }
+            None => {
+                res.push(s.as_bytes()[i]);
+                i += 1;
+            }
+        }
+    }
+    Cow::Owned(String::from_utf8_lossy(&res).into_owned())
+}
@@ -0,0 +1,235 @@
+//! Comments in fenced code blocks, e.g. `// 获取用户列表,然后渲染`.
+//!
+//! The languages in `code_comments` are scanned for comments by the fence's
+//! language tag, and the rules are applied to the comments with Chinese, the
+//! code and the strings are never touched.
+
+use std::ops::Range;
+
+use pulldown_cmark::{CodeBlockKind, Event, Tag};
+
+use crate::{char_kind::CharKindTrait, config::Config};
+
+/// The comment syntax of a language.
+struct Syntax {
+    line: &'static [&'static str],
+    block: &'static [(&'static str, &'static str)],
+    quotes: &'static [char],
+    /// Whether `'` starts a char literal like `'a'`, or it is a lifetime.
+    char_literals: bool,
+    /// Whether a line comment starts only at the line start or after a space,
+    /// e.g. `a#b` is not a comment.
+    spaced_line: bool,
+    /// Whether `${...}` is a parameter expansion, where no comment starts,
+    /// e.g. `${#arr}`.
+    expansions: bool,
+}
+
+const C_LIKE: Syntax = Syntax {
+    line: &["//"],
+    block: &[("/*", "*/")],
+    quotes: &['"', '\'', '`'],
+    char_literals: false,
+    spaced_line: false,
+    expansions: false,
+};
+const RUST: Syntax = Syntax {
+    line: &["//"],
+    block: &[("/*", "*/")],
+    quotes: &['"'],
+    char_literals: true,
+    spaced_line: false,
+    expansions: false,
+};
+const CSS: Syntax = Syntax {
+    line: &[],
+    block: &[("/*", "*/")],
+    quotes: &['"', '\''],
+    char_literals: false,
+    spaced_line: false,
+    expansions: false,
+};
+const SCRIPT: Syntax = Syntax {
+    line: &["#"],
+    block: &[],
+    quotes: &['"', '\''],
+    char_literals: false,
+    spaced_line: true,
+    expansions: true,
+};
+const MARKUP: Syntax = Syntax {
+    line: &[],
+    block: &[("<!--", "-->")],
+    quotes: &[],
+    char_literals: false,
+    spaced_line: false,
+    expansions: false,
+};
+
+fn get_syntax(lang: &str) -> Option<&'static Syntax> {
+    match lang {
+        "c" | "cpp" | "c++" | "cs" | "csharp" | "dart" | "go" | "java" | "javascript" | "js"
+        | "jsx" | "kotlin" | "kt" | "less" | "php" | "scala" | "scss" | "swift" | "ts" | "tsx"
+        | "typescript" => Some(&C_LIKE),
+        "rust" | "rs" => Some(&RUST),
+        "css" => Some(&CSS),
+        "bash" | "dockerfile" | "makefile" | "perl" | "py" | "python" | "r" | "rb" | "ruby"
+        | "sh" | "shell" | "toml" | "yaml" | "yml" | "zsh" => Some(&SCRIPT),
+        "html" | "markdown" | "md" | "svg" | "vue" | "xml" => Some(&MARKUP),
+        _ => None,
+    }
+}
+
+/// Format the comments in the fenced code blocks of the languages in
+/// `code_comments`, the texts of a code block are merged into one.
+pub(crate) fn lint_code_comments<'a>(
+    events: Vec<(Event<'a>, Range<usize>)>,
+    config: &Config,
+    lint: impl Fn(&str) -> String,
+) -> Vec<(Event<'a>, Range<usize>)> {
+    if config.code_comments.is_empty() {
+        return events;
+    }
+
+    let mut res: Vec<(Event, Range<usize>)> = Vec::with_capacity(events.len());
+    let mut syntax = None;
+    for (event, range) in events {
+        match &event {
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info))) => {
+                let lang = info.split_whitespace().next().unwrap_or_default();
+                syntax = config
+                    .code_comments
+                    .iter()
+                    .any(|x| x.eq_ignore_ascii_case(lang))
+                    .then(|| get_syntax(&lang.to_ascii_lowercase()))
+                    .flatten();
+            }
+            Event::End(Tag::CodeBlock(_)) => {
+                if let (Some(syntax), Some((Event::Text(code), _))) = (syntax, res.last_mut()) {
+                    *code = lint_code(code, syntax, &lint).into();
+                }
+                syntax = None;
+            }
+            Event::Text(s) if syntax.is_some() => {
+                if let Some((Event::Text(code), code_range)) = res.last_mut() {
+                    *code = format!("{code}{s}").into();
+                    code_range.end = range.end;
+                    continue;
+                }
+            }
+            _ => (),
+        }
+        res.push((event, range));
+    }
+    res
+}
+
+/// Find the comments in the code, and format them.
+fn lint_code(code: &str, syntax: &Syntax, lint: &impl Fn(&str) -> String) -> String {
+    let mut res = String::with_capacity(code.len());
+    let mut quote = None;
+    let mut i = 0;
+    while i < code.len() {
+        let rest = &code[i..];
+        let c = rest.chars().next().unwrap();
+
+        if let Some(q) = quote {
+            // strings end at the quote or the line end, except template strings
+            if c == '\\' {
+                let len = rest.chars().take(2).map(char::len_utf8).sum();
+                res.push_str(&rest[..len]);
+                i += len;
+                continue;
+            }
+            if c == q || (c == '\n' && q != '`') {
+                quote = None;
+            }
+            res.push(c);
+            i += c.len_utf8();
+            continue;
+        }
+
+        let spaced = i == 0 || code[..i].ends_with(char::is_whitespace);
+        if syntax.expansions && rest.starts_with("${") {
+            let end = find_closing_brace(rest).unwrap_or(rest.len());
+            res.push_str(&rest[..end]);
+            i += end;
+        } else if let Some(marker) = syntax
+            .line
+            .iter()
+            .find(|x| rest.starts_with(*x) && (spaced || !syntax.spaced_line))
+        {
+            let end = rest.find('\n').unwrap_or(rest.len());
+            res.push_str(marker);
+            res.push_str(&lint_comment_line(&rest[marker.len()..end], lint));
+            i += end;
+        } else if let Some((open, close)) = syntax.block.iter().find(|x| rest.starts_with(x.0)) {
+            let end = rest[open.len()..]
+                .find(close)
+                .map_or(rest.len(), |x| open.len() + x);
+            res.push_str(open);
+            for line in rest[open.len()..end].split_inclusive('\n') {
+                res.push_str(&lint_comment_line(line, lint));
+            }
+            i += end;
+        } else if let Some(len) = syntax.char_literals.then(|| char_literal(rest)).flatten() {
+            res.push_str(&rest[..len]);
+            i += len;
+        } else {
+            if syntax.quotes.contains(&c) {
+                quote = Some(c);
+            }
+            res.push(c);
+            i += c.len_utf8();
+        }
+    }
+    res
+}
+
+/// The end of the expansion at the start of `s`, after the `}`.
+fn find_closing_brace(s: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+/// The length of the char literal at the start of `s`, e.g. `'"'` or `'\n'`.
+fn char_literal(s: &str) -> Option<usize> {
+    let mut chars = s.char_indices().skip(1);
+    let (_, c) = chars.next().filter(|_| s.starts_with('\''))?;
+    if c == '\\' {
+        chars.next()?;
+    }
+    chars
+        .next()
+        .filter(|(_, c)| *c == '\'')
+        .map(|(i, c)| i + c.len_utf8())
+}
+
+/// Format a line of a comment with Chinese, the decorations like ` * ` at the
+/// start and the spaces around are kept.
+fn lint_comment_line(line: &str, lint: &impl Fn(&str) -> String) -> String {
+    if !line.chars().any(|c| c.is_full_width()) {
+        return line.to_string();
+    }
+    let content = line.trim_start_matches(|c: char| c.is_whitespace() || c == '*');
+    let start = line.len() - content.len();
+    let end = start + content.trim_end().len();
+    format!(
+        "{}{}{}",
+        &line[..start],
+        lint(&line[start..end]),
+        &line[end..]
+    )
+}
@@ -0,0 +1,107 @@
+use std::{error, fmt, path::Path};
+
+use regex::Regex;
+
+const UNKNOWN_FIELD_RE: &str = r"^unknown field `(?<field>[^`]*)`, expected (?<expected>.*)$";
+const EXPECTED_FIELD_RE: &str = r"`([^`]*)`";
+
+/// An error found while loading a config file.
+#[derive(Debug, Clone)]
+pub struct ConfigError {
+    /// The file or the command line argument containing the error.
+    pub path: Option<String>,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+    /// The closest valid key, if the error is caused by an unknown key.
+    pub suggestion: Option<String>,
+}
+
+impl ConfigError {
+    pub(crate) fn new(message: &str) -> Self {
+        let mut message = message.trim().to_string();
+        let suggestion = suggest_field(&message);
+        if suggestion.is_some() {
+            // the suggestion is more helpful than the full list of expected fields
+            message.truncate(message.find(", expected").unwrap_or(message.len()));
+        }
+        Self {
+            path: None,
+            line: None,
+            column: None,
+            message,
+            suggestion,
+        }
+    }
+
+    pub(crate) fn at(mut self, line: Option<usize>, column: Option<usize>) -> Self {
+        self.line = line;
+        self.column = column;
+        self
+    }
+
+    pub(crate) fn with_suggestion(mut self, suggestion: Option<String>) -> Self {
+        self.suggestion = suggestion;
+        self
+    }
+
+    pub(crate) fn with_path(mut self, path: &Path) -> Self {
+        self.path = Some(path.display().to_string());
+        self
+    }
+
+    pub(crate) fn with_arg(mut self, arg: &str) -> Self {
+        self.path = Some(arg.to_string());
+        self
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(path) = &self.path {
+            write!(f, "{path}:")?;
+        }
+        if let (Some(line), Some(column)) = (self.line, self.column) {
+            write!(f, "{line}:{column}:")?;
+        }
+        if self.path.is_some() || self.line.is_some() {
+            f.write_str(" ")?;
+        }
+        f.write_str(&self.message)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, "\n  help: did you mean `{suggestion}`?")?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for ConfigError {}
+
+/// Find the closest expected field for an unknown field error message.
+fn suggest_field(message: &str) -> Option<String> {
+    let captures = Regex::new(UNKNOWN_FIELD_RE).unwrap().captures(message)?;
+    let expected = Regex::new(EXPECTED_FIELD_RE)
+        .unwrap()
+        .captures_iter(&captures["expected"])
+        .map(|x| x[1].to_string())
+        .collect::<Vec<_>>();
+    closest(&captures["field"], &expected)
+}
+
+/// Find the candidate which is most similar to the given name.
+pub(crate) fn closest<T: AsRef<str>>(name: &str, candidates: &[T]) -> Option<String> {
+    candidates
+        .iter()
+        .map(|x| (strsim::jaro(name, x.as_ref()), x.as_ref()))
+        .filter(|(confidence, _)| *confidence > 0.7)
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, x)| x.to_string())
+}
+
+/// Convert a byte offset into a 1-based line and column.
+pub(crate) fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.chars().rev().take_while(|&c| c != '\n').count() + 1;
+    (line, column)
+}
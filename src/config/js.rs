@@ -0,0 +1,242 @@
+//! Configs of the JavaScript version of zhlint, which are JSON files named
+//! `.zhlintrc` or `.zhlintrc.json` with camelCase keys, e.g.
+//!
+//! ```json
+//! {
+//!   "preset": "default",
+//!   "rules": { "spaceOutsideCode": false, "skipZhUnits": "年月日" },
+//!   "ignores": ["vm\\.\\$on"]
+//! }
+//! ```
+
+use serde_json::{Map, Value};
+
+use super::{Config, ConfigError};
+
+/// Rules whose names are different in the JavaScript version.
+const RENAMED_RULES: [(&str, &str); 18] = [
+    ("halfwidthPunctuation", "half_width_punctuation"),
+    ("fullwidthPunctuation", "full_width_punctuation"),
+    (
+        "spaceBetweenHalfwidthContent",
+        "space_between_half_width_letters",
+    ),
+    (
+        "noSpaceBetweenFullwidthContent",
+        "no_space_between_full_width_letters",
+    ),
+    (
+        "spaceBetweenMixedwidthContent",
+        "space_between_mixed_width_letters",
+    ),
+    ("noSpaceBeforePauseOrStop", "no_space_before_punctuation"),
+    (
+        "spaceAfterHalfwidthPauseOrStop",
+        "space_after_half_width_punctuation",
+    ),
+    (
+        "noSpaceAfterFullwidthPauseOrStop",
+        "no_space_after_full_width_punctuation",
+    ),
+    ("spaceOutsideHalfwidthQuotation", "space_outside_half_quote"),
+    (
+        "noSpaceOutsideFullwidthQuotation",
+        "no_space_outside_full_quote",
+    ),
+    ("noSpaceInsideQuotation", "no_space_inside_quote"),
+    ("spaceOutsideHalfwidthBracket", "space_outside_half_bracket"),
+    (
+        "noSpaceOutsideFullwidthBracket",
+        "no_space_outside_full_bracket",
+    ),
+    ("noSpaceInsideBracket", "no_space_inside_bracket"),
+    ("noSpaceInsideHyperMark", "no_space_inside_wrapper"),
+    (
+        "spaceBetweenHalfWidthLetters",
+        "space_between_half_width_letters",
+    ),
+    (
+        "noSpaceBetweenFullWidthLetters",
+        "no_space_between_full_width_letters",
+    ),
+    (
+        "spaceBetweenMixedWidthLetters",
+        "space_between_mixed_width_letters",
+    ),
+];
+
+/// Options of the JavaScript version which are not supported yet.
+const UNSUPPORTED_OPTIONS: [&str; 6] = [
+    "noSinglePair",
+    "adjustedFullwidthPunctuation",
+    "skipPureWestern",
+    "hyperParse",
+    "hyperParsers",
+    "caseIgnores",
+];
+
+/// A config of the JavaScript version, converted into the keys of [`Config`].
+#[derive(Debug, Clone)]
+pub struct JsConfig {
+    pub value: Map<String, Value>,
+    /// Options which are dropped during the conversion.
+    pub warnings: Vec<String>,
+}
+
+impl JsConfig {
+    /// Parse and convert a JSON config, unknown keys are rejected.
+    pub fn parse(text: &str) -> Result<Self, ConfigError> {
+        let value: Value = serde_json::from_str(text)
+            .map_err(|e| ConfigError::new(&e.to_string()).at(Some(e.line()), Some(e.column())))?;
+        let Value::Object(map) = value else {
+            return Err(ConfigError::new("the config should be a JSON object"));
+        };
+
+        let mut res = JsConfig {
+            value: Map::new(),
+            warnings: Vec::new(),
+        };
+        for (key, value) in map {
+            match key.as_str() {
+                "rules" => {
+                    let rules = match value {
+                        Value::Object(rules) => res.convert_rules(rules),
+                        value => value,
+                    };
+                    res.value.insert(key, rules);
+                }
+                "ignores" => {
+                    let ignores = match value {
+                        Value::Array(ignores) => res.convert_ignores(ignores),
+                        value => value,
+                    };
+                    res.value.insert(key, ignores);
+                }
+                _ => res.insert(&key, value),
+            }
+        }
+
+        replace_nulls(
+            &mut res.value,
+            &serde_json::to_value(Config::empty()).unwrap(),
+        );
+        serde_json::from_value::<Config>(Value::Object(res.value.clone()))
+            .map_err(|e| ConfigError::new(&e.to_string()))?;
+        Ok(res)
+    }
+
+    fn insert(&mut self, key: &str, value: Value) {
+        if UNSUPPORTED_OPTIONS.contains(&key) {
+            self.warnings
+                .push(format!("`{key}` is not supported, ignored"));
+        } else {
+            self.value.insert(to_snake_case(key), value);
+        }
+    }
+
+    fn convert_rules(&mut self, rules: Map<String, Value>) -> Value {
+        let mut res = Map::new();
+        for (key, value) in rules {
+            if UNSUPPORTED_OPTIONS.contains(&key.as_str()) {
+                self.warnings
+                    .push(format!("`{key}` is not supported, ignored"));
+                continue;
+            }
+            let name = RENAMED_RULES
+                .iter()
+                .find(|(js_name, _)| *js_name == key)
+                .map(|(_, name)| name.to_string())
+                .unwrap_or_else(|| to_snake_case(&key));
+            let value = match (name.as_str(), value) {
+                ("unified_punctuation", Value::Object(_)) => {
+                    self.warnings.push(format!(
+                        "`{key}` with custom punctuations is not supported, ignored"
+                    ));
+                    continue;
+                }
+                ("skip_zh_units", Value::String(s)) => {
+                    s.chars().map(|c| Value::String(c.to_string())).collect()
+                }
+                (_, value) => value,
+            };
+            res.insert(name, value);
+        }
+        Value::Object(res)
+    }
+
+    /// Ignored cases are strings of regexes, or objects like
+    /// `{ "prefix": "", "textStart": "", "textEnd": "", "suffix": "" }`.
+    fn convert_ignores(&mut self, ignores: Vec<Value>) -> Value {
+        ignores
+            .into_iter()
+            .map(|ignore| match ignore {
+                Value::Object(case) => {
+                    let get = |key| {
+                        regex::escape(case.get(key).and_then(|x| x.as_str()).unwrap_or_default())
+                    };
+                    let text_end = get("textEnd");
+                    let text = if text_end.is_empty() {
+                        get("textStart")
+                    } else {
+                        format!("{}(?s:.*?){}", get("textStart"), text_end)
+                    };
+                    format!("{}(?<ignore>{}){}", get("prefix"), text, get("suffix")).into()
+                }
+                ignore => ignore,
+            })
+            .collect()
+    }
+
+    /// Convert into the content of a TOML file, with the keys which can not
+    /// be expressed in TOML.
+    pub fn to_toml(&self) -> (String, Vec<String>) {
+        let mut value = Value::Object(self.value.clone());
+        let mut dropped = Vec::new();
+        remove_nulls(&mut value, "", &mut dropped);
+        (toml::to_string_pretty(&value).unwrap(), dropped)
+    }
+}
+
+/// Options set to `null` do nothing, so they are replaced with the values of
+/// the `empty` preset, e.g. `false` or `[]`. The options doing nothing only
+/// when they are unset are kept `null`.
+fn replace_nulls(map: &mut Map<String, Value>, empty: &Value) {
+    for (key, value) in map.iter_mut() {
+        let Some(off) = empty.get(key) else {
+            continue;
+        };
+        match value {
+            Value::Null => *value = off.clone(),
+            Value::Object(map) => replace_nulls(map, off),
+            _ => (),
+        }
+    }
+}
+
+/// TOML has no `null`, so the options which are still `null` are removed.
+fn remove_nulls(value: &mut Value, path: &str, dropped: &mut Vec<String>) {
+    if let Value::Object(map) = value {
+        map.retain(|key, v| {
+            if v.is_null() {
+                dropped.push(format!("{path}{key}"));
+            }
+            !v.is_null()
+        });
+        for (key, v) in map.iter_mut() {
+            remove_nulls(v, &format!("{path}{key}."), dropped);
+        }
+    }
+}
+
+fn to_snake_case(s: &str) -> String {
+    let mut res = String::new();
+    for c in s.chars() {
+        if c.is_ascii_uppercase() {
+            res.push('_');
+            res.push(c.to_ascii_lowercase());
+        } else {
+            res.push(c);
+        }
+    }
+    res
+}
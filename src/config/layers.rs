@@ -0,0 +1,428 @@
+use std::{
+    collections::HashMap,
+    fmt, fs, iter,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use toml::Spanned;
+
+use super::{
+    error::{closest, line_column},
+    Config, ConfigError, JsConfig,
+};
+use crate::{
+    front_matter::{cut_front_matter, get_config},
+    ignore::compile_ignores,
+};
+
+const PRESETS: [&str; 2] = ["default", "empty"];
+const DEFAULT_PRESET: &str = "default";
+const IGNORES_KEY: &str = "ignores";
+
+/// The names of config files, the first existing one in a directory is used.
+pub const CONFIG_FILES: [&str; 3] = [".zhlintrc.toml", ".zhlintrc", ".zhlintrc.json"];
+
+/// The config files applying to `path`, one for each directory from the root
+/// down to the directory of `path`, so that the closer files are pushed later
+/// and override the ones of the parent directories.
+pub fn find_config_files<P: AsRef<Path>>(path: P) -> Vec<PathBuf> {
+    let path = path.as_ref();
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let dir = if path.is_dir() {
+        path.as_path()
+    } else {
+        path.parent().unwrap_or(Path::new(""))
+    };
+    let mut res: Vec<_> = dir
+        .ancestors()
+        .filter_map(|dir| {
+            CONFIG_FILES
+                .iter()
+                .map(|name| dir.join(name))
+                .find(|path| path.is_file())
+        })
+        .collect();
+    res.reverse();
+    res
+}
+
+/// Where a config value comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigSource {
+    Preset(String),
+    File(PathBuf),
+    CommandLine,
+    FrontMatter,
+    /// The defaults of a type of files, e.g. MDX for `.mdx` files.
+    FileType,
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigSource::Preset(name) => write!(f, "preset `{name}`"),
+            ConfigSource::File(path) if path.as_os_str().is_empty() => f.write_str("config"),
+            ConfigSource::File(path) => write!(f, "{}", path.display()),
+            ConfigSource::CommandLine => f.write_str("command line"),
+            ConfigSource::FrontMatter => f.write_str("front matter"),
+            ConfigSource::FileType => f.write_str("file type"),
+        }
+    }
+}
+
+/// The keys of a TOML file checked by their values, with the spans to point
+/// at.
+#[derive(Deserialize)]
+struct SpannedKeys {
+    preset: Option<Spanned<String>>,
+    #[serde(default)]
+    ignores: Vec<Spanned<String>>,
+}
+
+/// A stack of partial configs, values of the later layers override the
+/// earlier ones, and all of them are applied on top of the chosen preset.
+/// The `ignores` of all layers are collected instead of overridden. The front
+/// matter of a document goes before the command line, which overrides all.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigLayers {
+    /// A resolved config used instead of the preset.
+    base: Option<Config>,
+    layers: Vec<(ConfigSource, Map<String, Value>)>,
+    warnings: Vec<String>,
+}
+
+impl ConfigLayers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create layers on top of a resolved config instead of a preset.
+    pub fn with_base(config: Config) -> Self {
+        Self {
+            base: Some(config),
+            ..Default::default()
+        }
+    }
+
+    /// Push a layer from the content of a TOML file, unknown keys are rejected.
+    pub fn push_toml(&mut self, source: ConfigSource, text: &str) -> Result<(), ConfigError> {
+        let to_error = |e: toml::de::Error| {
+            let (line, column) = e.span().map(|span| line_column(text, span.start)).unzip();
+            ConfigError::new(e.message()).at(line, column)
+        };
+        // deserialize into `Config` first, so that errors point at the file
+        toml::from_str::<Config>(text).map_err(to_error)?;
+        let keys = toml::from_str::<SpannedKeys>(text).map_err(to_error)?;
+        let at = |e: ConfigError, span: Range<usize>| {
+            let (line, column) = line_column(text, span.start);
+            e.at(Some(line), Some(column))
+        };
+        if let Some(preset) = keys.preset {
+            if !PRESETS.contains(&preset.get_ref().as_str()) {
+                let e = ConfigError::new(&format!("unknown preset `{}`", preset.get_ref()))
+                    .with_suggestion(closest(preset.get_ref(), &PRESETS));
+                return Err(at(e, preset.span()));
+            }
+        }
+        for ignore in keys.ignores {
+            compile_ignores(&[ignore.get_ref()]).map_err(|e| at(e, ignore.span()))?;
+        }
+        let table = toml::from_str::<toml::Table>(text).map_err(to_error)?;
+        self.push(source, serde_json::to_value(table).unwrap());
+        Ok(())
+    }
+
+    /// Push a layer from the content of a JSON config of the JavaScript
+    /// version, see [`JsConfig`].
+    pub fn push_js(&mut self, source: ConfigSource, text: &str) -> Result<(), ConfigError> {
+        let js_config = JsConfig::parse(text)?;
+        self.warnings.extend(js_config.warnings);
+        self.push(source, Value::Object(js_config.value));
+        Ok(())
+    }
+
+    /// Read a config file and push it as a layer, files with the `.toml`
+    /// extension are TOML files and the others are JSON files.
+    pub fn push_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), ConfigError> {
+        let path = path.as_ref();
+        let source = ConfigSource::File(path.to_path_buf());
+        fs::read_to_string(path)
+            .map_err(|e| ConfigError::new(&format!("unable to read config file: {e}")))
+            .and_then(|text| match path.extension() {
+                Some(ext) if ext == "toml" => self.push_toml(source, &text),
+                _ => self.push_js(source, &text),
+            })
+            .map_err(|e| e.with_path(path))
+    }
+
+    /// Warnings about the options which are ignored while loading.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// Push a layer from the command line, `rules` are `key=value` pairs of
+    /// rule options, and `ignores` are regexes to be ignored.
+    pub fn push_args(&mut self, rules: &[String], ignores: &[String]) -> Result<(), ConfigError> {
+        let mut rules_map = Map::new();
+        for arg in rules {
+            let (key, value) = arg
+                .split_once('=')
+                .map(|(key, value)| (key.trim(), parse_arg_value(value.trim())))
+                .ok_or_else(|| ConfigError::new("expected `key=value`"))
+                .and_then(|(key, value)| {
+                    let key = key.strip_prefix("rules.").unwrap_or(key);
+                    let rule = Map::from_iter([(key.to_string(), value.clone())]);
+                    let config = Map::from_iter([("rules".to_string(), Value::Object(rule))]);
+                    serde_json::from_value::<Config>(Value::Object(config))
+                        .map_err(|e| ConfigError::new(&e.to_string()))?;
+                    Ok((key.to_string(), value))
+                })
+                .map_err(|e| e.with_arg(&format!("--rule {arg}")))?;
+            rules_map.insert(key, value);
+        }
+
+        let mut layer = Map::new();
+        if !rules_map.is_empty() {
+            layer.insert("rules".to_string(), Value::Object(rules_map));
+        }
+        for ignore in ignores {
+            compile_ignores(&[ignore]).map_err(|e| e.with_arg(&format!("--ignore {ignore}")))?;
+        }
+        if !ignores.is_empty() {
+            layer.insert(IGNORES_KEY.to_string(), ignores.into());
+        }
+        if !layer.is_empty() {
+            self.push(ConfigSource::CommandLine, Value::Object(layer));
+        }
+        Ok(())
+    }
+
+    /// Push the `zhlint` key of the front matter of a document as a layer.
+    /// The preset can not be changed by a document.
+    pub fn push_front_matter(&mut self, text: &str) -> Result<(), ConfigError> {
+        let (front_matter, _) = cut_front_matter(text);
+        if let Some(value) = get_config(front_matter).map_err(|e| e.with_arg("front matter"))? {
+            if value.get("preset").is_some() {
+                return Err(ConfigError::new("`preset` can not be set in front matter")
+                    .with_arg("front matter"));
+            }
+            let config = serde_json::from_value::<Config>(value.clone())
+                .map_err(|e| ConfigError::new(&e.to_string()).with_arg("front matter"))?;
+            compile_ignores(&config.ignores).map_err(|e| e.with_arg("front matter"))?;
+            self.push(ConfigSource::FrontMatter, value);
+        }
+        Ok(())
+    }
+
+    /// The layers of a document, with the `zhlint` key of its front matter
+    /// pushed before the command line layers, which still override it.
+    pub fn with_front_matter(&self, text: &str) -> Result<Self, ConfigError> {
+        let mut front_matter = ConfigLayers::new();
+        front_matter.push_front_matter(text)?;
+        let mut res = self.clone();
+        let i = res
+            .layers
+            .iter()
+            .position(|(source, _)| *source == ConfigSource::CommandLine)
+            .unwrap_or(res.layers.len());
+        res.layers.splice(i..i, front_matter.layers);
+        Ok(res)
+    }
+
+    fn push(&mut self, source: ConfigSource, value: Value) {
+        if let Value::Object(map) = value {
+            self.layers.push((source, map));
+        }
+    }
+
+    /// The name of the preset, which is decided by the last layer setting it.
+    pub fn preset(&self) -> &str {
+        self.layers
+            .iter()
+            .rev()
+            .find_map(|(_, layer)| layer.get("preset").and_then(|x| x.as_str()))
+            .or(self.base.as_ref().and_then(|x| x.preset.as_deref()))
+            .unwrap_or(DEFAULT_PRESET)
+    }
+
+    fn merged(&self) -> Result<Value, ConfigError> {
+        let base = match &self.base {
+            Some(base) => base.clone(),
+            None => {
+                let preset = self.preset();
+                Config::preset(preset).ok_or_else(|| {
+                    let e = ConfigError::new(&format!("unknown preset `{preset}`"))
+                        .with_suggestion(closest(preset, &PRESETS));
+                    let source = self
+                        .layers
+                        .iter()
+                        .rev()
+                        .find(|(_, layer)| layer.contains_key("preset"));
+                    match source {
+                        Some((source, _)) => with_source(e, source),
+                        None => e,
+                    }
+                })?
+            }
+        };
+        let mut res = serde_json::to_value(base).unwrap();
+        for (_, layer) in &self.layers {
+            merge(&mut res, layer);
+        }
+        Ok(res)
+    }
+
+    /// Apply all layers on top of the preset, the regexes of `ignores` are
+    /// checked with the source setting them.
+    pub fn resolve(&self) -> Result<Config, ConfigError> {
+        for (source, layer) in &self.layers {
+            let ignores = layer.get(IGNORES_KEY).and_then(|x| x.as_array());
+            let ignores: Vec<_> = ignores
+                .into_iter()
+                .flatten()
+                .filter_map(|x| x.as_str())
+                .collect();
+            compile_ignores(&ignores).map_err(|e| with_source(e, source))?;
+        }
+        let config: Config =
+            serde_json::from_value(self.merged()?).map_err(|e| ConfigError::new(&e.to_string()))?;
+        compile_ignores(&config.ignores)?;
+        Ok(config)
+    }
+
+    /// The source of every value in the resolved config, keyed by dotted path.
+    pub fn sources(&self) -> HashMap<String, ConfigSource> {
+        let mut res = HashMap::new();
+        let preset = ConfigSource::Preset(self.preset().to_string());
+        if let Ok(base) = self.merged() {
+            collect_sources(&mut res, "", &base, &preset);
+        }
+        for (source, layer) in &self.layers {
+            for (key, value) in layer {
+                collect_sources(&mut res, key, value, source);
+            }
+        }
+        res
+    }
+
+    /// Print the resolved config as TOML, annotating each value with its source.
+    pub fn to_annotated_toml(&self) -> Result<String, ConfigError> {
+        let config = serde_json::to_value(self.resolve()?).unwrap();
+        let sources = self.sources();
+
+        let mut res = String::new();
+        let mut tables = vec![(String::new(), config.as_object().unwrap())];
+        let mut i = 0;
+        while let Some((name, table)) = tables.get(i).cloned() {
+            if !name.is_empty() {
+                res.push_str(&format!("\n[{name}]\n"));
+            }
+            write_table(&mut res, &name, table, &sources, &mut tables);
+            i += 1;
+        }
+        Ok(res)
+    }
+}
+
+/// Point the error at the source of a layer.
+fn with_source(e: ConfigError, source: &ConfigSource) -> ConfigError {
+    match source {
+        ConfigSource::File(path) if !path.as_os_str().is_empty() => e.with_path(path),
+        ConfigSource::File(_) => e,
+        source => e.with_arg(&source.to_string()),
+    }
+}
+
+/// Values of command line arguments are TOML values, or strings if they are
+/// not legal TOML values. `null` means unsetting an option.
+fn parse_arg_value(value: &str) -> Value {
+    if value == "null" {
+        return Value::Null;
+    }
+    toml::from_str::<toml::Table>(&format!("value = {value}"))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .map(|value| serde_json::to_value(value).unwrap())
+        .unwrap_or_else(|| Value::String(value.to_string()))
+}
+
+/// Merge `layer` into `value`, tables are merged recursively and other values
+/// are replaced.
+fn merge(value: &mut Value, layer: &Map<String, Value>) {
+    let Value::Object(map) = value else {
+        *value = Value::Object(layer.clone());
+        return;
+    };
+    for (key, new_value) in layer {
+        match (map.get_mut(key), new_value) {
+            (Some(old_value @ Value::Object(_)), Value::Object(new_map)) => {
+                merge(old_value, new_map)
+            }
+            (Some(Value::Array(old_values)), Value::Array(new_values)) if key == IGNORES_KEY => {
+                old_values.extend(new_values.iter().cloned())
+            }
+            _ => {
+                map.insert(key.clone(), new_value.clone());
+            }
+        }
+    }
+}
+
+fn collect_sources(
+    res: &mut HashMap<String, ConfigSource>,
+    path: &str,
+    value: &Value,
+    source: &ConfigSource,
+) {
+    match value {
+        // the values of an empty table are the defaults of the table
+        Value::Object(map) if map.is_empty() && !path.is_empty() => {
+            res.insert(path.to_string(), source.clone());
+        }
+        Value::Object(map) => {
+            for (key, value) in map {
+                let path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{path}.{key}")
+                };
+                collect_sources(res, &path, value, source);
+            }
+        }
+        _ => {
+            res.insert(path.to_string(), source.clone());
+        }
+    }
+}
+
+fn write_table<'a>(
+    res: &mut String,
+    path: &str,
+    table: &'a Map<String, Value>,
+    sources: &HashMap<String, ConfigSource>,
+    tables: &mut Vec<(String, &'a Map<String, Value>)>,
+) {
+    for (key, value) in table {
+        let path = if path.is_empty() {
+            key.clone()
+        } else {
+            format!("{path}.{key}")
+        };
+        // the values of a table set as a whole come from the source of the table
+        let source = iter::successors(Some(path.as_str()), |x| x.rsplit_once('.').map(|x| x.0))
+            .find_map(|x| sources.get(x))
+            .map(|x| x.to_string())
+            .unwrap_or_default();
+        match value {
+            Value::Object(map) => tables.push((path, map)),
+            Value::Null => res.push_str(&format!("# {key} is unset  # {source}\n")),
+            _ => {
+                let value = toml::Value::try_from(value).unwrap();
+                res.push_str(&format!("{key} = {value}  # {source}\n"));
+            }
+        }
+    }
+}
@@ -0,0 +1,642 @@
+use std::path::{Path, PathBuf};
+
+use pulldown_cmark::Options;
+use schemars::{schema_for, JsonSchema};
+use serde::{Deserialize, Serialize};
+
+pub use self::{
+    error::ConfigError,
+    js::JsConfig,
+    layers::{find_config_files, ConfigLayers, ConfigSource, CONFIG_FILES},
+};
+
+mod error;
+mod js;
+mod layers;
+
+/// The Chinese script, which decides the style of quotes.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ZhScript {
+    #[default]
+    #[serde(alias = "Simplified")]
+    Simplified,
+    #[serde(alias = "Traditional")]
+    Traditional,
+}
+
+/// How to fix emphasis which is not parsed because of the punctuations inside.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EmphasisFix {
+    /// Add spaces outside, e.g. `**注意：** 文字`.
+    #[serde(alias = "Space")]
+    Space,
+    /// Rewrite into HTML, e.g. `<strong>注意：</strong>文字`.
+    #[serde(alias = "Html")]
+    Html,
+}
+
+/// The punctuation at the end of the items of a list.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ListItemEnding {
+    /// The ending of the most items in the list.
+    #[serde(alias = "Majority")]
+    Majority,
+    /// `；`
+    #[serde(alias = "Semicolon")]
+    Semicolon,
+    /// `。`
+    #[serde(alias = "Period")]
+    Period,
+    /// No punctuation.
+    #[serde(rename = "none", alias = "Nothing")]
+    Nothing,
+}
+
+/// Template syntax of static site generators, whose tags are kept as they are.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Template {
+    /// Hugo shortcodes, e.g. `{{< figure >}}` and `{{% note %}}`.
+    #[serde(alias = "Hugo")]
+    Hugo,
+    /// Jinja, e.g. `{% if x %}`, `{{ x }}` and `{# x #}`.
+    #[serde(alias = "Jinja")]
+    Jinja,
+    /// Liquid of Jekyll, e.g. `{% include x.html %}` and `{{ page.title }}`.
+    #[serde(alias = "Liquid")]
+    Liquid,
+    /// VuePress, e.g. `{{ $t('key') }}` and containers like `::: tip 提示`,
+    /// where the title is still formatted.
+    #[serde(rename = "vuepress", alias = "VuePress")]
+    VuePress,
+}
+
+/// Markdown elements, which are matched by the tags around the text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Element {
+    #[serde(alias = "Heading")]
+    Heading,
+    #[serde(alias = "Blockquote")]
+    Blockquote,
+    #[serde(alias = "List")]
+    List,
+    #[serde(alias = "Table")]
+    Table,
+    /// Footnote definitions, e.g. `[^1]: 脚注`.
+    #[serde(alias = "Footnote")]
+    Footnote,
+    /// The text of links, e.g. `文字` of `[文字](url)`.
+    #[serde(alias = "LinkText")]
+    LinkText,
+}
+
+/// The algorithm which generates the anchors of headings from the text.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Slugger {
+    /// GitHub, e.g. `使用 Vue 3.0` -> `使用-vue-30`.
+    #[default]
+    #[serde(rename = "github", alias = "GitHub")]
+    GitHub,
+    /// VuePress, e.g. `使用 Vue 3.0` -> `使用-vue-3-0`.
+    #[serde(rename = "vuepress", alias = "VuePress")]
+    VuePress,
+}
+
+/// What to do with the fragment links to the anchors changed by formatting.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AnchorLinks {
+    /// Update the links to the new anchors.
+    #[default]
+    #[serde(alias = "Update")]
+    Update,
+    /// Keep the links, and report them as diagnostics.
+    #[serde(alias = "Report")]
+    Report,
+}
+
+/// The formatting rules.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct Rules {
+    /* PUNCTUATIONS */
+    /// Convert these punctuations into half-width.
+    /// default preset: `()`
+    /// e.g. `（文字）` -> `(文字)`
+    pub half_width_punctuation: String,
+
+    /// Convert these punctuations into full-width.
+    /// default preset: `，。：；？！“”‘’`
+    /// e.g. `文字,文字.` -> `文字，文字。`
+    pub full_width_punctuation: String,
+
+    /// Convert traditional Chinese punctuations into simplified ones or vice versa.
+    /// default preset: `simplified`
+    /// e.g. `「文字」` -> `“文字”`
+    pub unified_punctuation: Option<ZhScript>,
+
+    /// Special case: skip `full_width_punctuation` for abbreviations.
+    /// default preset:
+    /// `['Mr.','Mrs.','Dr.','Jr.','Sr.','vs.','etc.','i.e.','e.g.','a.k.a']`
+    pub skip_abbrs: Vec<String>,
+
+    /* SPACES AROUND LETTERS */
+    /// default preset: `true`
+    /// - `true`: one space
+    /// - `false`: do nothing
+    ///
+    /// e.g. `foo  bar` -> `foo bar`
+    pub space_between_half_width_letters: bool,
+
+    /// default preset: `true`
+    /// - `true`: zero space
+    /// - `false`: do nothing
+    ///
+    /// e.g. `文 字` -> `文字`
+    pub no_space_between_full_width_letters: bool,
+
+    /// default preset: `true`
+    /// - `true`: one space
+    /// - `false`: zero space
+    /// - `null`: do nothing
+    ///
+    /// e.g. `文字 foo文字` -> `文字 foo 文字` (`true`)
+    /// e.g. `文字foo 文字` -> `文字foo文字` (`false`)
+    pub space_between_mixed_width_letters: Option<bool>,
+
+    /// Special case: skip `space_between_mixed_width_letters`
+    /// for numbers x Chinese units.
+    /// default preset: `['年','月','日','天','号','时','分','秒']`
+    pub skip_zh_units: Vec<char>,
+
+    /* SPACES AROUND PUNCTUATIONS */
+    /// default preset: `true`
+    /// - `true`: zero space
+    /// - `false`: do nothing
+    ///
+    /// e.g. `文字 ，文字` -> `文字，文字`
+    pub no_space_before_punctuation: bool,
+
+    /// default preset: `true`
+    /// - `true`: one space
+    /// - `false`: zero space
+    /// - `null`: do nothing
+    ///
+    /// e.g. `文字,文字` -> `文字, 文字` (`true`)
+    /// e.g. `文字, 文字` -> `文字,文字` (`false`)
+    pub space_after_half_width_punctuation: Option<bool>,
+
+    /// default preset: `true`
+    /// - `true`: zero space
+    /// - `false`: do nothing
+    ///
+    /// e.g. `文字， 文字` -> `文字，文字`
+    pub no_space_after_full_width_punctuation: bool,
+
+    /* SPACES AROUND QUOTES */
+    /// default preset: `true`
+    /// - `true`: one space
+    /// - `false`: zero space
+    /// - `null`: do nothing
+    ///
+    /// e.g. `文字 "文字"文字` -> `文字 "文字" 文字` (`true`)
+    /// e.g. `文字"文字" 文字` -> `文字"文字"文字` (`false`)
+    pub space_outside_half_quote: Option<bool>,
+
+    /// default preset: `true`
+    /// - `true`: zero space
+    /// - `false`: do nothing
+    ///
+    /// e.g. `文字 “文字” 文字` -> `文字“文字”文字`
+    pub no_space_outside_full_quote: bool,
+
+    /// default preset: `true`
+    /// - `true`: zero space
+    /// - `false`: do nothing
+    ///
+    /// e.g. `文字“ 文字 ”文字` -> `文字“文字”文字`
+    pub no_space_inside_quote: bool,
+
+    /* SPACES AROUND BRACKETS */
+    /// default preset: `true`
+    /// - `true`: one space
+    /// - `false`: zero space
+    /// - `null`: do nothing
+    pub space_outside_half_bracket: Option<bool>,
+
+    /// default preset: `true`
+    /// - `true`: zero space
+    /// - `false`: do nothing
+    pub no_space_outside_full_bracket: bool,
+
+    /// default preset: `true`
+    /// - `true`: zero space
+    /// - `false`: do nothing
+    pub no_space_inside_bracket: bool,
+
+    /* SPACES AROUND CODE */
+    /// default preset: `true`
+    /// - `true`: one space
+    /// - `false`: zero space
+    /// - `null`: do nothing
+    ///
+    /// e.g. '文字 `code`文字' -> '文字 `code` 文字' ('true')
+    /// e.g. '文字`code` 文字' -> '文字`code`文字' ('false')
+    pub space_outside_code: Option<bool>,
+
+    /// Inline HTML elements which are kept as they are, and spaced like
+    /// inline code by `space_outside_code`. Other tags like `<span>` are seen
+    /// through, and the text inside is checked as usual.
+    /// default preset: `["code", "kbd"]`
+    ///
+    /// e.g. `按<kbd>Ctrl</kbd>键` -> `按 <kbd>Ctrl</kbd> 键`
+    pub code_like_html_tags: Vec<String>,
+
+    /* SPACES AROUND MATH */
+    /// default preset: `null`
+    /// - `true`: one space
+    /// - `false`: zero space
+    /// - `null`: do nothing
+    ///
+    /// e.g. `复杂度$O(n)$的算法` -> `复杂度 $O(n)$ 的算法` (`true`)
+    /// e.g. `复杂度 $O(n)$ 的算法` -> `复杂度$O(n)$的算法` (`false`)
+    pub space_outside_math: Option<bool>,
+
+    /* SPACES AROUND MARKDOWN/HTML WRAPPERS */
+    /// default `true`
+    /// - `true`: zero space
+    /// - `false`: do nothing
+    ///
+    /// e.g. `文字** foo **文字` -> `文字 **foo** 文字`
+    pub no_space_inside_wrapper: bool,
+
+    /// Fix emphasis which is not parsed because of the punctuations inside.
+    /// default preset: `space`
+    /// - `space`: add spaces outside, if `space_between_mixed_width_letters` is `true`
+    /// - `html`: rewrite into `<strong>` or `<em>`
+    /// - `null`: do nothing
+    ///
+    /// e.g. `**注意：**文字` -> `**注意：** 文字` (`space`)
+    /// e.g. `**注意：**文字` -> `<strong>注意：</strong>文字` (`html`)
+    pub fix_emphasis: Option<EmphasisFix>,
+
+    /* LINE BREAKS INSIDE PARAGRAPHS */
+    /// default preset: `false`
+    /// - `true`: join the lines
+    /// - `false`: do nothing
+    ///
+    /// e.g. `文字\n文字` -> `文字文字`
+    pub no_line_break_between_full_width: bool,
+
+    /// Line breaks next to half-width content.
+    /// default preset: `false`
+    /// - `true`: one space
+    /// - `false`: do nothing
+    ///
+    /// e.g. `文字\nfoo` -> `文字 foo`
+    pub replace_line_break_with_space: bool,
+
+    /* SPACES AT THE BEGINNING/END */
+    /// Trim the spaces of paragraphs, headings, list items and table cells,
+    /// including `U+3000` and `U+00A0`.
+    /// default preset: `false`
+    /// - `true`: zero space
+    /// - `false`: do nothing
+    ///
+    /// e.g. `　文字 ` -> `文字`
+    pub trim_space: bool,
+
+    /* HEADINGS */
+    /// default preset: `false`
+    /// - `true`: remove the punctuations like `。`, `：` and `.` at the end
+    /// - `false`: do nothing
+    ///
+    /// e.g. `## 安装：` -> `## 安装`
+    pub no_end_punctuation_in_heading: bool,
+
+    /// default preset: `false`
+    /// - `true`: report the headings of the same level numbered in different
+    ///   styles, e.g. `一、` and `1.`
+    /// - `false`: do nothing
+    pub consistent_heading_numbering: bool,
+
+    /* LISTS */
+    /// The items of a list end with the same punctuation, `；`, `。` or
+    /// nothing. The items without Chinese or ending with other punctuations
+    /// are not checked.
+    /// default preset: `null`
+    /// - `majority`: the ending of the most items in the list
+    /// - `semicolon`, `period` or `none`: the given ending
+    /// - `null`: do nothing
+    pub consistent_list_item_ending: Option<ListItemEnding>,
+
+    /// default preset: `false`
+    /// - `true`: fix the endings of the items
+    /// - `false`: report the items
+    ///
+    /// e.g. `- 文字；\n- 文字；\n- 文字` -> `- 文字；\n- 文字；\n- 文字；` (`true`)
+    pub fix_list_item_ending: bool,
+}
+
+impl Rules {
+    pub fn empty() -> Self {
+        Self {
+            half_width_punctuation: String::new(),
+            full_width_punctuation: String::new(),
+            unified_punctuation: None,
+            skip_abbrs: Vec::new(),
+            space_between_half_width_letters: false,
+            no_space_between_full_width_letters: false,
+            space_between_mixed_width_letters: None,
+            skip_zh_units: Vec::new(),
+            no_space_before_punctuation: false,
+            space_after_half_width_punctuation: None,
+            no_space_after_full_width_punctuation: false,
+            space_outside_half_quote: None,
+            no_space_outside_full_quote: false,
+            no_space_inside_quote: false,
+            space_outside_half_bracket: None,
+            no_space_outside_full_bracket: false,
+            no_space_inside_bracket: false,
+            space_outside_code: None,
+            code_like_html_tags: Vec::new(),
+            space_outside_math: None,
+            no_space_inside_wrapper: false,
+            fix_emphasis: None,
+            trim_space: false,
+            no_end_punctuation_in_heading: false,
+            consistent_heading_numbering: false,
+            consistent_list_item_ending: None,
+            fix_list_item_ending: false,
+            no_line_break_between_full_width: false,
+            replace_line_break_with_space: false,
+        }
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            half_width_punctuation: "()".to_string(),
+            full_width_punctuation: "，。：；？！“”‘’".to_string(),
+            unified_punctuation: Some(ZhScript::Simplified),
+            skip_abbrs: vec![
+                "Mr.".to_string(),
+                "Mrs.".to_string(),
+                "Dr.".to_string(),
+                "Jr.".to_string(),
+                "Sr.".to_string(),
+                "vs.".to_string(),
+                "etc.".to_string(),
+                "i.e.".to_string(),
+                "e.g.".to_string(),
+                "a.k.a".to_string(),
+            ],
+            space_between_half_width_letters: true,
+            no_space_between_full_width_letters: true,
+            space_between_mixed_width_letters: Some(true),
+            skip_zh_units: vec!['年', '月', '日', '天', '号', '时', '分', '秒'],
+            no_space_before_punctuation: true,
+            space_after_half_width_punctuation: Some(true),
+            no_space_after_full_width_punctuation: true,
+            space_outside_half_quote: Some(true),
+            no_space_outside_full_quote: true,
+            no_space_inside_quote: true,
+            space_outside_half_bracket: Some(true),
+            no_space_outside_full_bracket: true,
+            no_space_inside_bracket: true,
+            space_outside_code: Some(true),
+            code_like_html_tags: vec!["code".to_string(), "kbd".to_string()],
+            space_outside_math: None,
+            no_space_inside_wrapper: true,
+            fix_emphasis: Some(EmphasisFix::Space),
+            trim_space: false,
+            no_end_punctuation_in_heading: false,
+            consistent_heading_numbering: false,
+            consistent_list_item_ending: None,
+            fix_list_item_ending: false,
+            no_line_break_between_full_width: false,
+            replace_line_break_with_space: false,
+        }
+    }
+}
+
+/// Markdown extensions, all of them except math are enabled by default.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct Extensions {
+    /// GFM tables, the rules are applied to the text of each cell.
+    pub tables: bool,
+    /// Footnote references and definitions, e.g. `[^1]`.
+    pub footnotes: bool,
+    /// Strikethrough, e.g. `~~text~~`.
+    pub strikethrough: bool,
+    /// Task lists, e.g. `- [x] done`.
+    pub tasklists: bool,
+    /// Math, e.g. `$O(n)$` or `$$...$$`, which is kept as it is. It is off
+    /// by default, as `$` is also used for prices and shell variables, so
+    /// enable it with `extensions = { math = true }` to protect the math from
+    /// the rules, and set `space_outside_math` for the spaces around.
+    pub math: bool,
+}
+
+impl Default for Extensions {
+    fn default() -> Self {
+        Self {
+            tables: true,
+            footnotes: true,
+            strikethrough: true,
+            tasklists: true,
+            math: false,
+        }
+    }
+}
+
+impl Extensions {
+    pub fn to_options(&self) -> Options {
+        let mut options = Options::empty();
+        options.set(Options::ENABLE_TABLES, self.tables);
+        options.set(Options::ENABLE_FOOTNOTES, self.footnotes);
+        options.set(Options::ENABLE_STRIKETHROUGH, self.strikethrough);
+        options.set(Options::ENABLE_TASKLISTS, self.tasklists);
+        options
+    }
+}
+
+/// Re-wrap paragraphs to a target width.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ProseWrap {
+    /// The max width of a line, counted in display columns, where a CJK
+    /// character takes two columns.
+    pub width: usize,
+}
+
+/// Which parts of links and images are formatted, the URLs and reference
+/// labels are never changed.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct Links {
+    /// Titles of links and images, e.g. `标题` of `[文字](url "标题")`.
+    /// default: `false`
+    pub titles: bool,
+    /// Alt text of images, e.g. `说明` of `![说明](a.png)`.
+    /// default: `true`
+    pub image_alts: bool,
+}
+
+impl Default for Links {
+    fn default() -> Self {
+        Self {
+            titles: false,
+            image_alts: true,
+        }
+    }
+}
+
+/// MDX, where ESM and JSX are kept as they are.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct Mdx {
+    /// String attributes of block JSX elements which are formatted, e.g.
+    /// `label` of `<TabItem label="示例">`.
+    /// default: `["label", "title"]`
+    pub attributes: Vec<String>,
+}
+
+impl Default for Mdx {
+    fn default() -> Self {
+        Self {
+            attributes: vec!["label".to_string(), "title".to_string()],
+        }
+    }
+}
+
+/// Keep the fragment links working when the anchors of headings are changed
+/// by formatting, e.g. `## 使用Vue` -> `## 使用 Vue` changes `#使用vue` into
+/// `#使用-vue`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct Anchors {
+    /// default: `github`
+    pub slugger: Slugger,
+    /// default: `update`
+    pub links: AnchorLinks,
+}
+
+/// The config of zhlint, usually loaded from `.zhlintrc.toml`.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The preset which the rules are based on, `default` or `empty`.
+    pub preset: Option<String>,
+    /// The formatting rules, override the rules of the preset.
+    pub rules: Rules,
+    /// Regexes of the content to be ignored, if there is a capture group named
+    /// `ignore`, only the content of the group is ignored.
+    pub ignores: Vec<String>,
+    /// Markdown elements whose text is kept as it is, e.g.
+    /// `["table", "blockquote", "heading", "link_text"]`.
+    pub skip_elements: Vec<Element>,
+    /// Keys of the front matter whose string values are formatted with the
+    /// rules, e.g. `["title", "description"]`.
+    pub front_matter_keys: Vec<String>,
+    /// Markdown extensions of GitHub Flavored Markdown.
+    pub extensions: Extensions,
+    /// Re-wrap paragraphs to a width, e.g. `prose_wrap = { width = 80 }`.
+    /// Lines are broken at spaces or between CJK characters, but never inside
+    /// a Latin word, inline code or a link.
+    pub prose_wrap: Option<ProseWrap>,
+    /// Template syntax whose tags are kept as they are, e.g.
+    /// `templates = ["hugo", "vuepress"]`.
+    pub templates: Vec<Template>,
+    /// Parse the documents as MDX, e.g. `mdx = {}`, which is enabled for
+    /// `.mdx` files by default. `import`/`export` lines, expressions and JSX
+    /// tags are kept as they are, but the prose children of elements and the
+    /// string values of `attributes` are formatted.
+    pub mdx: Option<Mdx>,
+    /// Languages of fenced code blocks whose comments are formatted, e.g.
+    /// `["js", "python"]`. Only the comments with Chinese are formatted, and
+    /// the code is never touched.
+    pub code_comments: Vec<String>,
+    /// Which parts of links and images are formatted, e.g.
+    /// `links = { titles = true }`.
+    pub links: Links,
+    /// Check the anchors of headings, e.g. `anchors = { slugger = "vuepress" }`.
+    /// The fragment links to the changed anchors in the formatted documents
+    /// are updated or reported.
+    pub anchors: Option<Anchors>,
+}
+
+impl Config {
+    pub fn empty() -> Self {
+        Self {
+            preset: None,
+            rules: Rules::empty(),
+            ignores: Vec::new(),
+            skip_elements: Vec::new(),
+            front_matter_keys: Vec::new(),
+            extensions: Extensions::default(),
+            prose_wrap: None,
+            templates: Vec::new(),
+            mdx: None,
+            code_comments: Vec::new(),
+            links: Links::default(),
+            anchors: None,
+        }
+    }
+
+    /// Get the config of a built-in preset.
+    pub fn preset(name: &str) -> Option<Self> {
+        let mut config = match name {
+            "default" => Self::default(),
+            "empty" => Self::empty(),
+            _ => return None,
+        };
+        config.preset = Some(name.to_string());
+        Some(config)
+    }
+
+    /// Generate the JSON Schema of the config file.
+    pub fn json_schema() -> String {
+        serde_json::to_string_pretty(&schema_for!(Config)).unwrap()
+    }
+
+    /// Parse a config from the content of a TOML file, unknown keys are rejected.
+    pub fn from_toml(text: &str) -> Result<Self, ConfigError> {
+        let mut layers = ConfigLayers::new();
+        layers.push_toml(ConfigSource::File(PathBuf::new()), text)?;
+        layers.resolve()
+    }
+
+    /// Read and parse a config file, TOML or JSON decided by the extension.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let mut layers = ConfigLayers::new();
+        layers.push_file(path)?;
+        layers.resolve()
+    }
+}
+
+impl From<Rules> for Config {
+    fn from(value: Rules) -> Self {
+        Config {
+            preset: None,
+            rules: value,
+            ignores: Vec::new(),
+            skip_elements: Vec::new(),
+            front_matter_keys: Vec::new(),
+            extensions: Extensions::default(),
+            prose_wrap: None,
+            templates: Vec::new(),
+            mdx: None,
+            code_comments: Vec::new(),
+            links: Links::default(),
+            anchors: None,
+        }
+    }
+}
@@ -0,0 +1,30 @@
+//! Problems which are found but not fixed, reported with the position in the
+//! source.
+
+/// A problem at a position of the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The byte offset in the source.
+    pub offset: usize,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn new(offset: usize, message: impl Into<String>) -> Self {
+        Self {
+            offset,
+            message: message.into(),
+        }
+    }
+
+    /// The line and the column in the source, starting from 1, the column is
+    /// counted in chars.
+    pub fn position(&self, text: &str) -> (usize, usize) {
+        let before = &text[..self.offset.min(text.len())];
+        let line_start = before.rfind('\n').map_or(0, |x| x + 1);
+        (
+            before.matches('\n').count() + 1,
+            before[line_start..].chars().count() + 1,
+        )
+    }
+}
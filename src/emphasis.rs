@@ -0,0 +1,330 @@
+//! Fix emphasis which is not parsed because of the punctuations inside.
+//!
+//! By the flanking rules of CommonMark, `**注意：**文字` is not strong
+//! emphasis, because the closing `**` is preceded by a punctuation and
+//! followed by a letter. The delimiter runs are left as text, and leak into
+//! rendered pages.
+//!
+//! Options:
+//! - fix_emphasis: Option<EmphasisFix>
+//!   - `space`: add spaces outside, if `space_between_mixed_width_letters`
+//!     is `true` (default)
+//!   - `html`: rewrite into `<strong>` or `<em>`
+//!   - `undefined`: do nothing, just keep the original format
+//!
+//! - no_space_inside_wrapper: bool
+//!   - `true`: `文字** foo **文字` is fixed into `文字**foo**文字`, the spaces
+//!     are moved out later by [`crate::wrapper`]
+//!   - `false`: do nothing
+//!
+//! Details:
+//! - only the delimiter runs next to full-width chars are fixed, the pairs
+//!   with spaces inside are also fixed if there is parsed emphasis in the
+//!   document, otherwise `2 ** 3 ** 2` is usually not emphasis
+//! - escaped delimiters like `\*` are kept
+
+use std::ops::Range;
+
+use pulldown_cmark::{Event, Tag};
+
+use crate::{
+    char_kind::CharKindTrait,
+    config::{Config, EmphasisFix},
+};
+
+/// A run of `*` or `_`, which is not parsed as a delimiter.
+struct Run {
+    /// The range of the events.
+    events: Range<usize>,
+    c: char,
+}
+
+/// Why a pair of runs is not parsed as emphasis.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PairKind {
+    /// The runs are next to full-width chars, e.g. `**注意：**文字`.
+    FullWidth,
+    /// There are spaces inside the runs, e.g. `** foo **`.
+    Spaced,
+}
+
+/// Decide the kind of a pair by the chars around the runs, `has_emphasis` is
+/// whether there is parsed emphasis in the document.
+fn pair_kind(
+    len: usize,
+    chars: [Option<char>; 4],
+    has_emphasis: bool,
+    config: &Config,
+) -> Option<PairKind> {
+    let [_, first, last, _] = chars;
+    let next_to_full_width = chars.iter().any(|c| c.is_some_and(|c| c.is_full_width()));
+    if first.is_none_or(char::is_whitespace) || last.is_none_or(char::is_whitespace) {
+        // a single `*` with spaces is usually an operator, e.g. `2 * 3 * 4`
+        return (config.rules.no_space_inside_wrapper
+            && len == 2
+            && (next_to_full_width || has_emphasis))
+            .then_some(PairKind::Spaced);
+    }
+    let has_fix = match config.rules.fix_emphasis {
+        Some(EmphasisFix::Space) => config.rules.space_between_mixed_width_letters == Some(true),
+        Some(EmphasisFix::Html) => true,
+        None => false,
+    };
+    (has_fix && next_to_full_width).then_some(PairKind::FullWidth)
+}
+
+/// A pair of runs, and whether spaces are needed outside of them.
+struct Fix {
+    kind: PairKind,
+    open: Run,
+    close: Run,
+    space_before: bool,
+    space_after: bool,
+}
+
+/// Runs which are not flanking, and `_` inside a word, are not split from the
+/// text by the parser. Split the pairs of runs which can be fixed, e.g.
+/// `前面__注意__文字` or `x ** yyy ** z`, so that they are found as other
+/// delimiter runs.
+fn split_runs<'a>(
+    events: Vec<(Event<'a>, Range<usize>)>,
+    text: &str,
+    has_emphasis: bool,
+    config: &Config,
+) -> Vec<(Event<'a>, Range<usize>)> {
+    let mut res = Vec::with_capacity(events.len());
+    for (event, range) in events {
+        let Event::Text(s) = &event else {
+            res.push((event, range));
+            continue;
+        };
+        // the offsets can not be mapped if there are escapes or entities
+        if !s.contains(['*', '_']) || &text[range.clone()] != s.as_ref() {
+            res.push((event, range));
+            continue;
+        }
+
+        let mut splits = Vec::new();
+        for c in ['*', '_'] {
+            let mut runs: Vec<Range<usize>> = Vec::new();
+            for (i, _) in s.match_indices(c) {
+                match runs.last_mut() {
+                    Some(run) if run.end == i => run.end += 1,
+                    _ => runs.push(i..i + 1),
+                }
+            }
+            let mut i = 0;
+            while i + 1 < runs.len() {
+                let (open, close) = (&runs[i], &runs[i + 1]);
+                let inner = &s[open.end..close.start];
+                let chars = [
+                    s[..open.start].chars().last(),
+                    inner.chars().next(),
+                    inner.chars().last(),
+                    s[close.end..].chars().next(),
+                ];
+                if open.len() == close.len()
+                    && open.len() <= 2
+                    && !inner.trim().is_empty()
+                    && pair_kind(open.len(), chars, has_emphasis, config).is_some()
+                {
+                    splits.extend([open.clone(), close.clone()]);
+                    i += 2;
+                } else {
+                    i += 1;
+                }
+            }
+        }
+        if splits.is_empty() {
+            res.push((event, range));
+            continue;
+        }
+        splits.sort_by_key(|x| x.start);
+
+        let mut start = 0;
+        for split in splits {
+            if start < split.start {
+                let piece = s[start..split.start].to_string();
+                res.push((
+                    Event::Text(piece.into()),
+                    range.start + start..range.start + split.start,
+                ));
+            }
+            for i in split.clone() {
+                res.push((
+                    Event::Text(s[i..i + 1].to_string().into()),
+                    range.start + i..range.start + i + 1,
+                ));
+            }
+            start = split.end;
+        }
+        if start < s.len() {
+            let piece = s[start..].to_string();
+            res.push((Event::Text(piece.into()), range.start + start..range.end));
+        }
+    }
+    res
+}
+
+fn get_runs(events: &[(Event, Range<usize>)], text: &str) -> Vec<Run> {
+    let is_delimiter = |(event, range): &(Event, Range<usize>)| match event {
+        Event::Text(s)
+            if (s.as_ref() == "*" || s.as_ref() == "_") && !text[..range.start].ends_with('\\') =>
+        {
+            s.chars().next()
+        }
+        _ => None,
+    };
+
+    let mut runs: Vec<Run> = Vec::new();
+    for (i, event) in events.iter().enumerate() {
+        let Some(c) = is_delimiter(event) else {
+            continue;
+        };
+        match runs.last_mut() {
+            Some(run) if run.events.end == i && run.c == c => run.events.end += 1,
+            _ => runs.push(Run {
+                events: i..i + 1,
+                c,
+            }),
+        }
+    }
+    runs
+}
+
+/// The first or last char of an inline event, inline code is treated as a
+/// letter, and other events are treated as whitespace.
+fn edge_char(event: Option<&(Event, Range<usize>)>, last: bool) -> Option<char> {
+    match event {
+        Some((Event::Text(s), _)) if last => s.chars().last(),
+        Some((Event::Text(s), _)) => s.chars().next(),
+        Some((Event::Code(_), _)) => Some('a'),
+        _ => None,
+    }
+}
+
+fn is_letter(c: Option<char>) -> bool {
+    c.is_some_and(|c| !c.is_whitespace() && !c.is_punctuation())
+}
+
+fn get_fixes(
+    events: &[(Event, Range<usize>)],
+    runs: Vec<Run>,
+    has_emphasis: bool,
+    config: &Config,
+) -> Vec<Fix> {
+    let mut fixes = Vec::new();
+    let mut runs = runs.into_iter().peekable();
+    while let Some(open) = runs.next() {
+        let Some(close) = runs.peek() else {
+            break;
+        };
+        let len = open.events.len();
+        if open.c != close.c
+            || !(1..=2).contains(&len)
+            || close.events.len() != len
+            || open.events.end == close.events.start
+            || !events[open.events.end..close.events.start]
+                .iter()
+                .all(|(event, _)| matches!(event, Event::Text(_) | Event::Code(_)))
+        {
+            continue;
+        }
+
+        let before = edge_char(open.events.start.checked_sub(1).map(|i| &events[i]), true);
+        let first = edge_char(events.get(open.events.end), false);
+        let last = edge_char(events.get(close.events.start - 1), true);
+        let after = edge_char(events.get(close.events.end), false);
+        let is_blank = events[open.events.end..close.events.start]
+            .iter()
+            .all(|(event, _)| matches!(event, Event::Text(s) if s.trim().is_empty()));
+        let Some(kind) = pair_kind(len, [before, first, last, after], has_emphasis, config)
+            .filter(|_| !is_blank)
+        else {
+            continue;
+        };
+
+        // `_` can not open or close inside a word
+        let (space_before, space_after) = match open.c {
+            '*' => (
+                is_letter(before) && !is_letter(first),
+                is_letter(after) && !is_letter(last),
+            ),
+            _ => (is_letter(before), is_letter(after)),
+        };
+        let close = runs.next().unwrap();
+        fixes.push(Fix {
+            kind,
+            open,
+            close,
+            space_before,
+            space_after,
+        });
+    }
+    fixes
+}
+
+/// Rewrite the delimiter runs of the unparsed emphasis into events.
+pub(crate) fn fix_emphasis<'a>(
+    events: Vec<(Event<'a>, Range<usize>)>,
+    text: &str,
+    config: &Config,
+) -> Vec<(Event<'a>, Range<usize>)> {
+    let has_emphasis = events
+        .iter()
+        .any(|(event, _)| matches!(event, Event::Start(Tag::Emphasis | Tag::Strong)));
+    let mut events = split_runs(events, text, has_emphasis, config);
+    let fixes = get_fixes(&events, get_runs(&events, text), has_emphasis, config);
+
+    for Fix {
+        kind,
+        open,
+        close,
+        space_before,
+        space_after,
+    } in fixes.into_iter().rev()
+    {
+        let tag = if open.events.len() == 1 {
+            Tag::Emphasis
+        } else {
+            Tag::Strong
+        };
+        let html = if open.events.len() == 1 {
+            "em"
+        } else {
+            "strong"
+        };
+        let open_range = events[open.events.start].1.start..events[open.events.end - 1].1.end;
+        let close_range = events[close.events.start].1.start..events[close.events.end - 1].1.end;
+        let space = |offset: usize| (Event::Text(" ".into()), offset..offset);
+
+        let (open_events, close_events) = match (kind, config.rules.fix_emphasis) {
+            (PairKind::FullWidth, Some(EmphasisFix::Html)) => (
+                vec![(Event::Html(format!("<{html}>").into()), open_range)],
+                vec![(Event::Html(format!("</{html}>").into()), close_range)],
+            ),
+            (PairKind::Spaced, _) => (
+                vec![(Event::Start(tag.clone()), open_range)],
+                vec![(Event::End(tag), close_range)],
+            ),
+            _ => {
+                if !space_before && !space_after {
+                    // the emphasis is not parsed for other reasons
+                    continue;
+                }
+                let mut open_events = vec![(Event::Start(tag.clone()), open_range.clone())];
+                if space_before {
+                    open_events.insert(0, space(open_range.start));
+                }
+                let mut close_events = vec![(Event::End(tag), close_range.clone())];
+                if space_after {
+                    close_events.push(space(close_range.end));
+                }
+                (open_events, close_events)
+            }
+        };
+        events.splice(close.events, close_events);
+        events.splice(open.events, open_events);
+    }
+    events
+}
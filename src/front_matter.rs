@@ -0,0 +1,265 @@
+//! The YAML (`---`) or TOML (`+++`) front matter at the beginning of a
+//! document, which is written back untouched, except the string values of
+//! `front_matter_keys` are formatted, and the `zhlint` key of it is used as
+//! the config of the document, e.g.
+//!
+//! ```yaml
+//! ---
+//! title: 标题
+//! zhlint:
+//!   rules:
+//!     space_outside_code: false
+//! ---
+//! ```
+
+use regex::Regex;
+use serde_json::Value;
+
+use crate::config::ConfigError;
+
+const FRONT_MATTER_DELIMITERS: [&str; 2] = ["---\n", "+++\n"];
+const CONFIG_KEY: &str = "zhlint";
+/// The `zhlint` key at the top level, e.g. `zhlint:`, `zhlint =` or `[zhlint]`.
+const CONFIG_KEY_RE: &str = r"(?m)^(zhlint[ \t]*[:=.]|\[\[?[ \t]*zhlint[ \t]*[.\]])";
+
+/// Split the text into the front matter (with delimiters) and the content.
+pub(crate) fn cut_front_matter(text: &str) -> (&str, &str) {
+    for front_matter_delimiter in FRONT_MATTER_DELIMITERS {
+        if let Some(slice) = text.strip_prefix(front_matter_delimiter) {
+            // the ending delimiter is a whole line, e.g. not `title: a---`
+            let index_of_ending_line = if slice.starts_with(front_matter_delimiter) {
+                Some(0)
+            } else {
+                slice
+                    .find(&format!("\n{front_matter_delimiter}"))
+                    .map(|x| x + 1)
+            };
+            if let Some(index_of_ending_line) = index_of_ending_line {
+                return text.split_at(index_of_ending_line + front_matter_delimiter.len() * 2);
+            }
+        }
+    }
+
+    ("", text)
+}
+
+/// Get the value of the `zhlint` key in the front matter. The front matter
+/// which does not parse is not used, unless it has the `zhlint` key.
+pub(crate) fn get_config(front_matter: &str) -> Result<Option<Value>, ConfigError> {
+    let Some(delimiter) = FRONT_MATTER_DELIMITERS
+        .into_iter()
+        .find(|x| front_matter.starts_with(x))
+    else {
+        return Ok(None);
+    };
+    let content = &front_matter[delimiter.len()..front_matter.len() - delimiter.len()];
+
+    let value = if delimiter == "+++\n" {
+        toml::from_str::<toml::Table>(content)
+            .map_err(|e| ConfigError::new(e.message()))
+            .map(|x| serde_json::to_value(x).unwrap())
+    } else {
+        serde_yaml::from_str::<serde_yaml::Value>(content)
+            .map_err(|e| ConfigError::new(&e.to_string()))
+            .map(|x| serde_json::to_value(x).unwrap_or_default())
+    };
+    match value {
+        Ok(value) => Ok(value.get(CONFIG_KEY).cloned()),
+        Err(e) if Regex::new(CONFIG_KEY_RE).unwrap().is_match(content) => Err(e),
+        Err(_) => Ok(None),
+    }
+}
+
+const YAML_KEY_RE: &str = r"^(?<key>[A-Za-z0-9_-]+):(?<sep>[ \t]+)(?<value>.*)$";
+const TOML_KEY_RE: &str = r"^(?<key>[A-Za-z0-9_-]+)(?<sep>[ \t]*=[ \t]*)(?<value>.*)$";
+const YAML_BLOCK_SCALAR_RE: &str = r"^[|>][+-]?[0-9]?[+-]?[ \t]*(#.*)?$";
+const YAML_PLAIN_INDICATORS: &str = "-?:,[]{}#&*!|>'\"%@`";
+
+/// Format the string values of the given top-level keys with `format`, the
+/// quoting styles and the rest of the front matter are kept untouched.
+pub(crate) fn format_front_matter<F: Fn(&str) -> String>(
+    front_matter: &str,
+    keys: &[String],
+    format: F,
+) -> String {
+    let is_toml = front_matter.starts_with("+++\n");
+    let key_re = Regex::new(if is_toml { TOML_KEY_RE } else { YAML_KEY_RE }).unwrap();
+    let block_scalar_re = Regex::new(YAML_BLOCK_SCALAR_RE).unwrap();
+
+    let mut res = String::with_capacity(front_matter.len());
+    let mut in_block_scalar = false;
+    let mut in_top_level = true;
+
+    for line in front_matter.split_inclusive('\n') {
+        let (content, eol) = line.split_at(line.trim_end_matches(['\n', '\r']).len());
+
+        if in_block_scalar {
+            let indent = content.len() - content.trim_start().len();
+            if indent > 0 || content.is_empty() {
+                res.push_str(&content[..indent]);
+                res.push_str(&format(&content[indent..]));
+                res.push_str(eol);
+                continue;
+            }
+            in_block_scalar = false;
+        }
+        if is_toml && content.trim_start().starts_with('[') {
+            in_top_level = false;
+        }
+
+        let captures = key_re
+            .captures(content)
+            .filter(|x| in_top_level && keys.iter().any(|key| key == &x["key"]));
+        let Some(captures) = captures else {
+            res.push_str(line);
+            continue;
+        };
+        let value = captures.name("value").unwrap();
+        let (prefix, value) = content.split_at(value.start());
+
+        if !is_toml && block_scalar_re.is_match(value) {
+            in_block_scalar = true;
+            res.push_str(line);
+            continue;
+        }
+
+        let formatted = if is_toml {
+            format_toml_value(value, &format)
+        } else {
+            format_yaml_value(value, &format)
+        };
+        res.push_str(prefix);
+        res.push_str(formatted.as_deref().unwrap_or(value));
+        res.push_str(eol);
+    }
+
+    res
+}
+
+fn format_yaml_value<F: Fn(&str) -> String>(value: &str, format: &F) -> Option<String> {
+    match value.chars().next()? {
+        '"' => format_quoted(value, '"', decode_basic, encode_basic, format),
+        '\'' => format_quoted(value, '\'', decode_single, encode_single, format),
+        c if YAML_PLAIN_INDICATORS.contains(c) => None,
+        _ => {
+            let end = value.find(" #").unwrap_or(value.len());
+            let (text, rest) = value.split_at(value[..end].trim_end().len());
+            let formatted = format(text);
+            if formatted == text
+                || formatted.is_empty()
+                || formatted.contains(": ")
+                || formatted.contains(" #")
+                || formatted.ends_with(':')
+                || formatted.starts_with(|c| YAML_PLAIN_INDICATORS.contains(c))
+            {
+                return None;
+            }
+            Some(formatted + rest)
+        }
+    }
+}
+
+fn format_toml_value<F: Fn(&str) -> String>(value: &str, format: &F) -> Option<String> {
+    if value.starts_with("\"\"\"") || value.starts_with("'''") {
+        return None;
+    }
+    match value.chars().next()? {
+        '"' => format_quoted(value, '"', decode_basic, encode_basic, format),
+        '\'' => format_quoted(value, '\'', |s| Some(s.to_string()), encode_literal, format),
+        _ => None,
+    }
+}
+
+/// Format a quoted value, the rest after the closing quote is kept.
+fn format_quoted<F: Fn(&str) -> String>(
+    value: &str,
+    quote: char,
+    decode: fn(&str) -> Option<String>,
+    encode: fn(&str) -> Option<String>,
+    format: &F,
+) -> Option<String> {
+    let end = closing_quote(&value[1..], quote)? + 1;
+    let text = decode(&value[1..end])?;
+    let formatted = format(&text);
+    if formatted == text {
+        return None;
+    }
+    Some(format!(
+        "{quote}{}{quote}{}",
+        encode(&formatted)?,
+        &value[end + 1..]
+    ))
+}
+
+/// Find the closing quote, `\` escapes in double quotes and `''` in single
+/// quotes are skipped.
+fn closing_quote(s: &str, quote: char) -> Option<usize> {
+    let mut chars = s.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' if quote == '"' => {
+                chars.next();
+            }
+            '\'' if quote == '\'' && chars.peek().is_some_and(|x| x.1 == '\'') => {
+                chars.next();
+            }
+            c if c == quote => return Some(i),
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Decode double-quoted strings, `None` for unsupported escapes.
+fn decode_basic(s: &str) -> Option<String> {
+    let mut res = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            res.push(c);
+            continue;
+        }
+        res.push(match chars.next()? {
+            '\\' => '\\',
+            '"' => '"',
+            '/' => '/',
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            'u' => {
+                let hex: String = chars.by_ref().take(4).collect();
+                char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?
+            }
+            _ => return None,
+        });
+    }
+    Some(res)
+}
+
+fn encode_basic(s: &str) -> Option<String> {
+    let mut res = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => res.push_str("\\\\"),
+            '"' => res.push_str("\\\""),
+            '\n' => res.push_str("\\n"),
+            '\t' => res.push_str("\\t"),
+            '\r' => res.push_str("\\r"),
+            c => res.push(c),
+        }
+    }
+    Some(res)
+}
+
+fn decode_single(s: &str) -> Option<String> {
+    Some(s.replace("''", "'"))
+}
+
+fn encode_single(s: &str) -> Option<String> {
+    Some(s.replace('\'', "''"))
+}
+
+/// TOML literal strings can not contain single quotes.
+fn encode_literal(s: &str) -> Option<String> {
+    (!s.contains(['\'', '\n'])).then(|| s.to_string())
+}
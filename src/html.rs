@@ -0,0 +1,132 @@
+//! Inline HTML, which comes from the parser as raw events of each tag.
+//!
+//! - elements of the tags in `code_like_html_tags`, e.g. `<kbd>Ctrl</kbd>`,
+//!   are merged into one raw event, which is kept as it is and spaced like
+//!   inline code
+//! - other tags, e.g. `<span>`, are seen through by the rules like emphasis,
+//!   so that the text inside is checked as usual
+
+use std::ops::Range;
+
+use pulldown_cmark::Event;
+
+use crate::{config::Config, parser::is_inline};
+
+/// Elements which have no content and no closing tag.
+const VOID_ELEMENTS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// A single opening or closing tag, e.g. `<span class="x">` or `</span>`.
+pub(crate) struct HtmlTag<'a> {
+    pub name: &'a str,
+    pub closing: bool,
+}
+
+impl<'a> HtmlTag<'a> {
+    pub fn parse(s: &'a str) -> Option<Self> {
+        let s = s.strip_prefix('<')?.strip_suffix('>')?;
+        let (s, closing) = match s.strip_prefix('/') {
+            Some(s) => (s, true),
+            None => (s, false),
+        };
+        let end = s
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '-')
+            .unwrap_or(s.len());
+        let (name, rest) = s.split_at(end);
+        let is_valid = name.starts_with(|c: char| c.is_ascii_alphabetic())
+            && !rest.contains(['<', '>'])
+            && match closing {
+                true => rest.trim().is_empty(),
+                false => rest.is_empty() || rest.starts_with(char::is_whitespace) || rest == "/",
+            };
+        is_valid.then_some(Self { name, closing })
+    }
+
+    /// Whether the tag opens or closes an element with content.
+    pub fn is_wrapper(&self) -> bool {
+        !VOID_ELEMENTS.contains(&self.name.to_ascii_lowercase().as_str())
+    }
+}
+
+/// Whether the event is inline code, or an element merged by
+/// [`merge_code_like_html`].
+pub fn is_code_like(event: &Event, config: &Config) -> bool {
+    let Event::Html(s) = event else {
+        return matches!(event, Event::Code(_));
+    };
+    // a single tag is code-like only if it is a void element, e.g. `<br>`
+    if HtmlTag::parse(s).is_some_and(|tag| tag.is_wrapper()) {
+        return false;
+    }
+    let name = s.strip_prefix('<').and_then(|s| {
+        s.split(|c: char| c == '>' || c == '/' || c.is_whitespace())
+            .next()
+    });
+    name.is_some_and(|name| is_listed(name, config))
+}
+
+fn is_listed(name: &str, config: &Config) -> bool {
+    config
+        .rules
+        .code_like_html_tags
+        .iter()
+        .any(|x| x.eq_ignore_ascii_case(name))
+}
+
+/// Merge the elements of the tags in `code_like_html_tags` into one raw event
+/// from the source, so that the content is never touched by the rules.
+pub(crate) fn merge_code_like_html<'a>(
+    mut events: Vec<(Event<'a>, Range<usize>)>,
+    text: &str,
+    config: &Config,
+) -> Vec<(Event<'a>, Range<usize>)> {
+    if config.rules.code_like_html_tags.is_empty() {
+        return events;
+    }
+    let mut i = 0;
+    while i < events.len() {
+        let opening = match &events[i].0 {
+            Event::Html(s) => HtmlTag::parse(s)
+                .filter(|tag| !tag.closing && tag.is_wrapper() && is_listed(tag.name, config))
+                .map(|tag| tag.name.to_string()),
+            _ => None,
+        };
+        let Some(name) = opening else {
+            i += 1;
+            continue;
+        };
+
+        // the closing tag must be in the same paragraph
+        let mut depth = 0;
+        let mut close = None;
+        for (j, (event, _)) in events.iter().enumerate().skip(i + 1) {
+            match event {
+                Event::Html(s) => match HtmlTag::parse(s) {
+                    Some(tag) if tag.name.eq_ignore_ascii_case(&name) && tag.closing => {
+                        if depth == 0 {
+                            close = Some(j);
+                            break;
+                        }
+                        depth -= 1;
+                    }
+                    Some(tag) if tag.name.eq_ignore_ascii_case(&name) => depth += 1,
+                    _ => (),
+                },
+                Event::Start(tag) | Event::End(tag) if !is_inline(tag) => break,
+                _ => (),
+            }
+        }
+        let Some(close) = close else {
+            i += 1;
+            continue;
+        };
+
+        let range = events[i].1.start..events[close].1.end;
+        let html = text[range.clone()].to_string();
+        events.splice(i..=close, [(Event::Html(html.into()), range)]);
+        i += 1;
+    }
+    events
+}
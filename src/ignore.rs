@@ -1,7 +1,9 @@
 use std::ops::Range;
 
 use pulldown_cmark::{Event, Parser};
-use regex::{Error, Regex};
+use regex::Regex;
+
+use crate::config::ConfigError;
 
 const IGNORE_CAPTURE_NAME: &str = "ignore";
 const DISABLE_HTML_RE: &str = r"^\s*<!--\s*zhlint disabled\s*-->\s*$";
@@ -35,13 +37,35 @@ pub(crate) fn get_ignore_list_from_events(events: Parser) -> Ignore {
     Ignore::Ignore(res)
 }
 
-pub(crate) fn get_ignore_ranges<T: AsRef<str>>(
-    text: &str,
-    ignores: &[T],
-) -> Result<Vec<Range<usize>>, Error> {
+/// Whether the range overlaps any of the ignored ranges.
+pub(crate) fn is_ignored(range: &Range<usize>, ignore_ranges: &[Range<usize>]) -> bool {
+    ignore_ranges
+        .iter()
+        .any(|x| x.start < range.end && range.start < x.end)
+}
+
+/// Compile the regexes of `ignores`, the error names the invalid regex.
+pub(crate) fn compile_ignores<T: AsRef<str>>(ignores: &[T]) -> Result<Vec<Regex>, ConfigError> {
+    ignores
+        .iter()
+        .map(|x| {
+            Regex::new(x.as_ref()).map_err(|e| {
+                // the last line of a syntax error is the reason
+                let e = e.to_string();
+                let reason = e.lines().last().unwrap_or_default();
+                let reason = reason.strip_prefix("error: ").unwrap_or(reason);
+                ConfigError::new(&format!(
+                    "invalid regex `{}` in ignores: {reason}",
+                    x.as_ref()
+                ))
+            })
+        })
+        .collect()
+}
+
+pub(crate) fn get_ignore_ranges(text: &str, ignores: &[Regex]) -> Vec<Range<usize>> {
     let mut res = Vec::new();
-    for ignore_regex in ignores {
-        let re = Regex::new(ignore_regex.as_ref())?;
+    for re in ignores {
         if re
             .capture_names()
             .any(|x| x.is_some_and(|x| x == IGNORE_CAPTURE_NAME))
@@ -51,12 +75,8 @@ pub(crate) fn get_ignore_ranges<T: AsRef<str>>(
                     .map(|x| x.name(IGNORE_CAPTURE_NAME).map(|x| x.range()).unwrap()),
             );
         } else {
-            res.extend(
-                Regex::new(ignore_regex.as_ref())?
-                    .find_iter(text)
-                    .map(|x| x.range()),
-            );
+            res.extend(re.find_iter(text).map(|x| x.range()));
         }
     }
-    Ok(res)
+    res
 }
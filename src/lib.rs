@@ -1,20 +1,54 @@
-use std::{fmt, iter};
+use std::{borrow::Cow, error, fmt, iter, ops::Range};
 
-use pulldown_cmark::{Event, Options, Parser, Tag};
-use pulldown_cmark_to_cmark::cmark;
+use pulldown_cmark::{Event, Parser, Tag};
+use pulldown_cmark_to_cmark::{cmark_resume, State};
+use regex::Regex;
+use unicode_width::UnicodeWidthStr;
 
 use crate::{
-    config::Config,
-    ignore::{get_ignore_list_from_events, get_ignore_ranges, Ignore},
-    parser::EventCursor,
-    rules::rules,
+    anchor::{
+        get_anchor_changes, get_anchors, get_fragment_links, report_anchor_links,
+        update_anchor_links, AnchorChange,
+    },
+    code_comment::lint_code_comments,
+    config::{AnchorLinks, Config, ConfigError, ConfigLayers},
+    diagnostic::Diagnostic,
+    emphasis::fix_emphasis,
+    front_matter::{cut_front_matter, format_front_matter},
+    html::merge_code_like_html,
+    ignore::{compile_ignores, get_ignore_list_from_events, get_ignore_ranges, Ignore},
+    link::{get_definitions, prepare_links, reference_source},
+    math::{get_math_events, protect_math},
+    mdx::protect_mdx,
+    parser::{EventCursor, TextCursor},
+    rules::{check_rules, event_rules, rules},
+    skip::protect_elements,
+    table::{protect_ragged_tables, write_table},
+    template::protect_templates,
+    wrap::wrap_inlines,
+    wrapper::move_spaces_out,
 };
 
+pub mod anchor;
 pub mod char_kind;
+mod code_comment;
 pub mod config;
+pub mod diagnostic;
+mod emphasis;
+mod front_matter;
+mod html;
 pub mod ignore;
+mod link;
+mod math;
+mod mdx;
 pub mod parser;
+mod protect;
 pub mod rules;
+mod skip;
+mod table;
+mod template;
+mod wrap;
+mod wrapper;
 
 #[derive(Debug, Clone, Default)]
 pub struct Context {
@@ -22,6 +56,9 @@ pub struct Context {
 
     pub half_width_single_quote_count: u32,
     pub half_width_double_quote_count: u32,
+
+    /// The ranges of the math spans in the source.
+    pub math_ranges: Vec<Range<usize>>,
 }
 
 impl Context {
@@ -35,41 +72,136 @@ impl Context {
     }
 }
 
-const FRONT_MATTER_DELIMITERS: [&str; 2] = ["---\n", "+++\n"];
+#[derive(Debug)]
+pub enum Error {
+    Fmt(fmt::Error),
+    Config(ConfigError),
+}
 
-fn cut_front_matter(text: &str) -> (&str, &str) {
-    for front_matter_delimiter in FRONT_MATTER_DELIMITERS {
-        if let Some(slice) = text.strip_prefix(front_matter_delimiter) {
-            if let Some(index_of_ending_line) = slice.find(front_matter_delimiter) {
-                return text.split_at(index_of_ending_line + front_matter_delimiter.len() * 2);
-            }
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Fmt(e) => e.fmt(f),
+            Error::Config(e) => e.fmt(f),
         }
     }
+}
+
+impl error::Error for Error {}
 
-    ("", text)
+impl From<fmt::Error> for Error {
+    fn from(value: fmt::Error) -> Self {
+        Error::Fmt(value)
+    }
 }
 
-pub fn run<W: fmt::Write>(text: &str, config: &Config, mut writer: W) -> Result<(), fmt::Error> {
-    let (front_matter, text) = cut_front_matter(text);
+impl From<ConfigError> for Error {
+    fn from(value: ConfigError) -> Self {
+        Error::Config(value)
+    }
+}
 
-    let rules = rules();
+/// What is found when formatting a document, besides the output.
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    /// The problems which are not fixed.
+    pub diagnostics: Vec<Diagnostic>,
+    /// The anchors of headings changed by formatting, if `anchors` is set.
+    pub anchor_changes: Vec<AnchorChange>,
+}
+
+pub fn run<W: fmt::Write>(text: &str, config: &Config, writer: W) -> Result<(), Error> {
+    run_with_report(text, config, writer).map(|_| ())
+}
 
-    let options = Options::empty();
+/// Format the document like [`run`], and report what is found.
+pub fn run_with_report<W: fmt::Write>(
+    text: &str,
+    config: &Config,
+    writer: W,
+) -> Result<Report, Error> {
+    if cut_front_matter(text).0.is_empty() {
+        return format_document(text, config, writer);
+    }
+    run_with_layers(text, &ConfigLayers::with_base(config.clone()), writer)
+}
+
+/// Format the document like [`run_with_report`], with the config resolved
+/// from `layers` and the front matter of the document, see
+/// [`ConfigLayers::with_front_matter`].
+pub fn run_with_layers<W: fmt::Write>(
+    text: &str,
+    layers: &ConfigLayers,
+    writer: W,
+) -> Result<Report, Error> {
+    let config = layers.with_front_matter(text)?.resolve()?;
+    format_document(text, &config, writer)
+}
 
-    let mut ignore = match get_ignore_list_from_events(Parser::new_ext(text, options)) {
+/// Format the document with the config already resolved from its front matter.
+fn format_document<W: fmt::Write>(
+    text: &str,
+    config: &Config,
+    mut writer: W,
+) -> Result<Report, Error> {
+    let (front_matter, text) = cut_front_matter(text);
+    let offset = front_matter.len();
+
+    let options = config.extensions.to_options();
+
+    let ignore = match get_ignore_list_from_events(Parser::new_ext(text, options)) {
         Ignore::Disabled => {
             writer.write_str(front_matter)?;
             writer.write_str(text)?;
-            return Ok(());
+            return Ok(Report::default());
         }
         Ignore::Ignore(ignore) => ignore,
     };
-    ignore.append(&mut config.ignores.clone());
+    let config_ignores = compile_ignores(&config.ignores)?;
+    let mut ignores = compile_ignores(&ignore).map_err(|e| e.with_arg("ignore comment"))?;
+    ignores.extend(config_ignores.iter().cloned());
+    let lint_str = |s: &str| lint_str(s, config, &config_ignores);
+
+    let front_matter = &if config.front_matter_keys.is_empty() {
+        Cow::Borrowed(front_matter)
+    } else {
+        Cow::Owned(format_front_matter(
+            front_matter,
+            &config.front_matter_keys,
+            lint_str,
+        ))
+    };
 
-    let ignore_ranges = get_ignore_ranges(text, &ignore).unwrap();
+    let ignore_ranges = get_ignore_ranges(text, &ignores);
 
-    let mut event_cursor = EventCursor::new(Parser::new_ext(text, options).into_offset_iter());
+    let mut events = Parser::new_ext(text, options).into_offset_iter().collect();
+    let definitions = get_definitions(text, options);
+    events = prepare_links(events, text, definitions, config, lint_str);
+    events = protect_mdx(events, text, config, lint_str);
+    events = lint_code_comments(events, config, lint_str);
+    let mut math_ranges = Vec::new();
+    if config.extensions.math {
+        events = protect_math(events, text);
+        math_ranges = get_math_events(&events, text);
+    }
+    events = protect_templates(events, text, config);
+    events = merge_code_like_html(events, text, config);
+    events = protect_elements(events, text, config);
+    events = protect_ragged_tables(events, text);
+    let events = move_spaces_out(fix_emphasis(events, text, config), config);
+    let mut report = Report::default();
+    for rule in check_rules() {
+        report.diagnostics.extend(rule(&events, config));
+    }
+    let anchors = config.anchors.as_ref().map(|anchors| {
+        (
+            get_anchors(events.iter().map(|x| &x.0), anchors.slugger),
+            get_fragment_links(&events),
+        )
+    });
+    let mut event_cursor = EventCursor::new(events);
     let mut context = Context::new();
+    context.math_ranges = math_ranges;
 
     let t = iter::from_fn(|| {
         if let Some(event) = &event_cursor.current_event {
@@ -93,55 +225,179 @@ pub fn run<W: fmt::Write>(text: &str, config: &Config, mut writer: W) -> Result<
                 Event::End(Tag::CodeBlock(_)) => context.code_block_mark = false,
                 Event::Text(_) if !context.code_block_mark => {
                     let mut text_cursor = event_cursor.to_text_cursor().unwrap();
-
-                    loop {
-                        if text_cursor.current() == '\'' {
-                            context.half_width_single_quote_count += 1;
-                        }
-                        if text_cursor.current() == '"' {
-                            context.half_width_double_quote_count += 1;
-                        }
-
-                        text_cursor.skip_str(&config.rules.skip_abbrs);
-
-                        let mut skip_flag = false;
-
-                        for ignore_range in &ignore_ranges {
-                            if let Some(current_offset) = text_cursor.current_offset() {
-                                if ignore_range.contains(&current_offset) {
-                                    skip_flag = true;
-                                    break;
-                                }
-                            }
-                        }
-
-                        if !skip_flag {
-                            for rule in &rules {
-                                rule(&context, &mut text_cursor, config);
-                            }
-                        }
-
-                        if !text_cursor.advance() {
-                            break;
-                        }
-                    }
-
+                    lint_text_cursor(&mut text_cursor, &mut context, config, &ignore_ranges);
                     res = Event::Text(String::from(text_cursor).into());
                 }
                 _ => (),
             };
+            if let Some(source) = reference_source(&event.0, &event.1, text) {
+                res = Event::Html(source.into());
+            }
+            let range = event.1.clone();
             event_cursor.advance();
-            Some(res)
+            Some((res, range))
         } else {
             None
         }
     });
 
     writer.write_str(front_matter)?;
-    cmark(t, writer)?;
+    let mut events = t.collect::<Vec<_>>();
+    for rule in event_rules() {
+        rule(&mut events, config, &ignore_ranges);
+    }
+    let mut events: Vec<_> = events.into_iter().map(|x| x.0).collect();
+    if let (Some(config), Some((old, links))) = (&config.anchors, anchors) {
+        let changes = get_anchor_changes(old, get_anchors(&events, config.slugger));
+        match config.links {
+            AnchorLinks::Update => update_anchor_links(&mut events, &changes),
+            AnchorLinks::Report => {
+                report
+                    .diagnostics
+                    .extend(report_anchor_links(&links, &changes, 0));
+            }
+        }
+        report.anchor_changes = changes;
+    }
+    report.diagnostics.sort_by_key(|x| x.offset);
+    for diagnostic in &mut report.diagnostics {
+        diagnostic.offset += offset;
+    }
+    write_events(&events, config, writer)?;
+    Ok(report)
+}
+
+/// Write the events as markdown, tables are written by ourselves, and
+/// paragraphs are re-wrapped if `prose_wrap` is set.
+fn write_events<W: fmt::Write>(events: &[Event], config: &Config, mut writer: W) -> fmt::Result {
+    let is_special = |x: &Event| match x {
+        Event::Start(Tag::Table(_)) => true,
+        // tight list items contain inline events without paragraphs
+        Event::Start(Tag::Paragraph | Tag::Item) => config.prose_wrap.is_some(),
+        _ => false,
+    };
+
+    let mut state = State::default();
+    let mut rest = events;
+    while let Some(start) = rest.iter().position(is_special) {
+        match (&rest[start], &config.prose_wrap) {
+            (Event::Start(Tag::Table(_)), _) => {
+                let end = start
+                    + rest[start..]
+                        .iter()
+                        .position(|x| matches!(x, Event::End(Tag::Table(_))))
+                        .unwrap();
+                state = cmark_resume(rest[..start].iter(), &mut writer, Some(state))?;
+                write_table(&rest[start..=end], &mut state, &mut writer)?;
+                rest = &rest[end + 1..];
+            }
+            (_, Some(prose_wrap)) => {
+                let end = start
+                    + 1
+                    + rest[start + 1..]
+                        .iter()
+                        .position(|x| !is_inline(x))
+                        .unwrap_or(rest.len() - start - 1);
+                let mut prefix = String::new();
+                state = cmark_resume(rest[..=start].iter(), &mut prefix, Some(state))?;
+                writer.write_str(&prefix)?;
+                // the first line follows the markers, e.g. `[^1]: ` or `1. `
+                let first_indent = prefix.rsplit('\n').next().unwrap_or_default().width();
+                let indent = state.padding.concat().width();
+                let events = wrap_inlines(
+                    &rest[start + 1..end],
+                    prose_wrap.width.saturating_sub(first_indent),
+                    prose_wrap.width.saturating_sub(indent),
+                );
+                state = cmark_resume(events.iter(), &mut writer, Some(state))?;
+                rest = &rest[end..];
+            }
+            _ => unreachable!(),
+        }
+    }
+    state = cmark_resume(rest.iter(), &mut writer, Some(state))?;
+    state.finalize(writer)?;
     Ok(())
 }
 
+/// Whether the event is an inline event, which can be in a paragraph.
+fn is_inline(event: &Event) -> bool {
+    match event {
+        Event::Start(tag) | Event::End(tag) => matches!(
+            tag,
+            Tag::Emphasis | Tag::Strong | Tag::Strikethrough | Tag::Link(..) | Tag::Image(..)
+        ),
+        Event::Rule => false,
+        _ => true,
+    }
+}
+
+/// Apply the rules on every char of the text cursor.
+fn lint_text_cursor(
+    text_cursor: &mut TextCursor,
+    context: &mut Context,
+    config: &Config,
+    ignore_ranges: &[Range<usize>],
+) {
+    if text_cursor.is_empty() {
+        return;
+    }
+    let rules = rules();
+
+    loop {
+        if text_cursor.current() == '\'' {
+            context.half_width_single_quote_count += 1;
+        }
+        if text_cursor.current() == '"' {
+            context.half_width_double_quote_count += 1;
+        }
+
+        text_cursor.skip_str(&config.rules.skip_abbrs);
+        if text_cursor.is_end() {
+            break;
+        }
+
+        let mut skip_flag = false;
+
+        for ignore_range in ignore_ranges {
+            if let Some(current_offset) = text_cursor.current_offset() {
+                if ignore_range.contains(&current_offset) {
+                    skip_flag = true;
+                    break;
+                }
+            }
+        }
+
+        if !skip_flag {
+            for rule in &rules {
+                // the whole text may be deleted, e.g. a single space
+                if text_cursor.is_end() {
+                    return;
+                }
+                rule(context, text_cursor, config);
+            }
+        }
+
+        if !text_cursor.advance() {
+            break;
+        }
+    }
+}
+
+/// Apply the rules on a plain text, which is not parsed as markdown, with the
+/// compiled `ignores` of the config.
+pub(crate) fn lint_str(text: &str, config: &Config, ignores: &[Regex]) -> String {
+    let ignore_ranges = get_ignore_ranges(text, ignores);
+    let mut text_cursor = TextCursor::new(text, 0);
+    lint_text_cursor(
+        &mut text_cursor,
+        &mut Context::new(),
+        config,
+        &ignore_ranges,
+    );
+    text_cursor.into()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
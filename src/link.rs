@@ -0,0 +1,252 @@
+//! Links and images, whose URLs and reference labels are never changed.
+//!
+//! - the text of links is formatted as other text
+//! - the alt text of images is formatted if `links.image_alts` is `true`
+//! - the titles are formatted if `links.titles` is `true`
+//! - reference links are written as they are in the source, e.g.
+//!   `[文字][label]`, and collapsed or shortcut links like `[label]` are kept,
+//!   because the text is the label
+//! - link reference definitions are written back as they are, except the
+//!   titles, in the blockquotes or list items where they are
+
+use std::ops::Range;
+
+use pulldown_cmark::{Event, LinkType, Options, Parser, Tag};
+
+use crate::{config::Config, parser::is_inline, protect::protect_ranges};
+
+/// A link reference definition, e.g. `[label]: https://example.com "标题"`.
+pub(crate) struct Definition {
+    pub(crate) span: Range<usize>,
+    title: Option<String>,
+}
+
+pub(crate) fn get_definitions(text: &str, options: Options) -> Vec<Definition> {
+    let parser = Parser::new_ext(text, options);
+    let mut res: Vec<Definition> = parser
+        .reference_definitions()
+        .iter()
+        .map(|(_, x)| Definition {
+            span: x.span.clone(),
+            title: x.title.as_ref().map(|x| x.to_string()),
+        })
+        .collect();
+    res.sort_by_key(|x| x.span.start);
+    res
+}
+
+fn is_reference(link_type: LinkType) -> bool {
+    matches!(link_type, LinkType::Reference | LinkType::ReferenceUnknown)
+}
+
+fn is_label(link_type: LinkType) -> bool {
+    matches!(
+        link_type,
+        LinkType::Collapsed
+            | LinkType::CollapsedUnknown
+            | LinkType::Shortcut
+            | LinkType::ShortcutUnknown
+    )
+}
+
+/// Format the titles, keep the labels and the alt text if needed, and insert
+/// the link reference definitions, which are not in the events.
+pub(crate) fn prepare_links<'a>(
+    events: Vec<(Event<'a>, Range<usize>)>,
+    text: &str,
+    definitions: Vec<Definition>,
+    config: &Config,
+    lint: impl Fn(&str) -> String,
+) -> Vec<(Event<'a>, Range<usize>)> {
+    let mut res: Vec<(Event, Range<usize>)> = Vec::with_capacity(events.len());
+    let mut alt_ranges = Vec::new();
+    let mut image_start = None;
+    let mut events = events.into_iter();
+    while let Some((event, range)) = events.next() {
+        let event = match event {
+            Event::Start(Tag::Link(link_type, ..) | Tag::Image(link_type, ..))
+                if is_label(link_type) =>
+            {
+                let mut depth = 1;
+                for (event, _) in events.by_ref() {
+                    match event {
+                        Event::Start(_) => depth += 1,
+                        Event::End(_) => depth -= 1,
+                        _ => (),
+                    }
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                // the empty label of a collapsed link is not in the range
+                let mut range = range;
+                if text[range.end..].starts_with("[]") {
+                    range.end += 2;
+                }
+                res.push((Event::Html(text[range.clone()].to_string().into()), range));
+                continue;
+            }
+            Event::Start(Tag::Image(..)) => {
+                image_start = Some(res.len() + 1);
+                event
+            }
+            Event::End(Tag::Image(..)) => {
+                if let Some(start) = image_start.take().filter(|x| *x < res.len()) {
+                    alt_ranges.push(res[start].1.start..res[res.len() - 1].1.end);
+                }
+                event
+            }
+            event => event,
+        };
+        let event = match event {
+            Event::Start(tag) if config.links.titles => Event::Start(lint_title(tag, &lint)),
+            Event::End(tag) if config.links.titles => Event::End(lint_title(tag, &lint)),
+            event => event,
+        };
+        res.push((event, range));
+    }
+    if !config.links.image_alts {
+        res = protect_ranges(res, text, alt_ranges);
+    }
+
+    let definitions = definitions.into_iter().map(|definition| {
+        // the definition is a block, ended with the line break
+        let span = &definition.span;
+        let end = text[span.end..]
+            .find('\n')
+            .map_or(text.len(), |x| span.end + x + 1);
+        let mut source = text[span.start..end].to_string();
+        if let Some(title) = definition.title.as_deref().filter(|_| config.links.titles) {
+            if let Some(i) = source.rfind(title) {
+                source.replace_range(i..i + title.len(), &lint(title));
+            }
+        }
+        (Event::Html(source.into()), span.start..end)
+    });
+    // the definitions are inserted between the blocks where they are, before
+    // the next block, or before the end of the container, e.g. a blockquote
+    let len = res.len();
+    let mut definitions = definitions
+        .map(|definition| {
+            let start = definition.1.start;
+            let i = res
+                .iter()
+                .position(|(event, range)| match event {
+                    Event::End(tag) => !is_inline(tag) && range.end > start,
+                    _ => range.start >= start,
+                })
+                .unwrap_or(len);
+            (i, definition)
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+        .peekable();
+    let mut merged = Vec::with_capacity(res.len());
+    let mut depth = 0;
+    for (i, event) in res.into_iter().enumerate() {
+        let mut inserted = Vec::new();
+        while let Some((_, definition)) = definitions.next_if(|x| x.0 == i) {
+            inserted.push(definition);
+        }
+        merged.extend(in_container(inserted, depth, Some(&event.0)));
+        match &event.0 {
+            Event::Start(tag) if !is_inline(tag) => depth += 1,
+            Event::End(tag) if !is_inline(tag) => depth -= 1,
+            _ => (),
+        }
+        merged.push(event);
+    }
+    merged.extend(definitions.map(|(_, definition)| definition));
+    merged
+}
+
+/// The definitions inside a container like a blockquote or a list item, the
+/// prefixes of the container like `> ` are removed from the source, as the
+/// writer adds them. They are written as a paragraph before another block,
+/// as the writer does not prefix the line after a raw block.
+fn in_container<'a>(
+    definitions: Vec<(Event<'a>, Range<usize>)>,
+    depth: usize,
+    next: Option<&Event>,
+) -> Vec<(Event<'a>, Range<usize>)> {
+    let (Some(first), Some(last)) = (definitions.first(), definitions.last()) else {
+        return definitions;
+    };
+    if depth == 0 {
+        return definitions;
+    }
+    let range = first.1.start..last.1.end;
+    let mut source = definitions
+        .iter()
+        .filter_map(|(event, _)| match event {
+            Event::Html(s) => Some(s.as_ref()),
+            _ => None,
+        })
+        .collect::<String>()
+        .lines()
+        .enumerate()
+        .map(|(i, line)| match i {
+            0 => line,
+            _ => line.trim_start_matches([' ', '\t', '>']),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    match next {
+        Some(Event::Start(tag)) if !is_inline(tag) => vec![
+            (Event::Start(Tag::Paragraph), range.clone()),
+            (Event::Html(source.into()), range.clone()),
+            (Event::End(Tag::Paragraph), range),
+        ],
+        // the inline content of a tight list item follows in the next line
+        Some(Event::End(_)) | None => vec![(Event::Html(source.into()), range)],
+        Some(_) => {
+            source.push('\n');
+            vec![(Event::Html(source.into()), range)]
+        }
+    }
+}
+
+fn lint_title<'a>(tag: Tag<'a>, lint: &impl Fn(&str) -> String) -> Tag<'a> {
+    match tag {
+        Tag::Link(link_type, url, title) if !title.is_empty() => {
+            Tag::Link(link_type, url, lint(&title).into())
+        }
+        Tag::Image(link_type, url, title) if !title.is_empty() => {
+            Tag::Image(link_type, url, lint(&title).into())
+        }
+        tag => tag,
+    }
+}
+
+/// The source of the brackets of a reference link, e.g. `[` and `][label]` of
+/// `[文字][label]`, which is written instead of the event, so that the label is
+/// kept.
+pub(crate) fn reference_source(event: &Event, range: &Range<usize>, text: &str) -> Option<String> {
+    match event {
+        Event::Start(Tag::Link(link_type, ..)) if is_reference(*link_type) => Some("[".into()),
+        Event::Start(Tag::Image(link_type, ..)) if is_reference(*link_type) => Some("![".into()),
+        Event::End(Tag::Link(link_type, ..) | Tag::Image(link_type, ..))
+            if is_reference(*link_type) =>
+        {
+            let source = &text[range.clone()];
+            source.rfind('[').map(|i| format!("]{}", &source[i..]))
+        }
+        _ => None,
+    }
+}
+
+/// The range of the URL in the source of a link reference definition, e.g.
+/// `#标题` of `[label]: #标题 "title"`.
+pub(crate) fn definition_url(s: &str) -> Option<Range<usize>> {
+    if !s.trim_start().starts_with('[') {
+        return None;
+    }
+    let colon = s.find("]:")? + 2;
+    let start = colon + (s[colon..].len() - s[colon..].trim_start().len());
+    let rest = &s[start..];
+    if let Some(rest) = rest.strip_prefix('<') {
+        return rest.find('>').map(|x| start + 1..start + 1 + x);
+    }
+    let len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+    Some(start..start + len)
+}
@@ -1,36 +1,266 @@
-use std::{fs, path::PathBuf, process::exit};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    process::exit,
+};
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use glob::glob;
 
-use zhlint::{config::Config, run};
+use zhlint::{
+    anchor::{update_fragment_links, AnchorChange},
+    config::{find_config_files, Config, ConfigLayers, ConfigSource, JsConfig, CONFIG_FILES},
+    diagnostic::Diagnostic,
+    run_with_layers,
+};
 
 /// A formatter tool for Chinese text content.
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, subcommand_negates_reqs = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// File pattern
     #[arg(default_value_t = String::from("./**/*.md"))]
     path: String,
 
-    /// Config file path, .zhlintrc.toml by default
-    #[arg(short, long, default_value_os_t = PathBuf::from(".zhlintrc.toml"))]
-    config: PathBuf,
+    /// Config file path, by default the .zhlintrc.toml, .zhlintrc or
+    /// .zhlintrc.json of the directory of each file and its parents
+    #[arg(short, long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Override a rule option, e.g. `--rule space_outside_code=false`
+    #[arg(long = "rule", value_name = "KEY=VALUE", global = true)]
+    rules: Vec<String>,
+
+    /// Ignore the content matched by the regex, in addition to the config
+    #[arg(long = "ignore", value_name = "REGEX", global = true)]
+    ignores: Vec<String>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Inspect the config file
+    #[command(subcommand)]
+    Config(ConfigCommand),
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigCommand {
+    /// Validate the config file without formatting any files
+    Check,
+    /// Print the effective config for a file, with the source of each value
+    Print {
+        /// The file to resolve the config for
+        file: PathBuf,
+    },
+    /// Print the JSON Schema of the config file
+    Schema,
+    /// Convert a JSON config of the JavaScript version into TOML
+    Migrate {
+        /// The JSON config, .zhlintrc or .zhlintrc.json by default
+        input: Option<PathBuf>,
+
+        /// Write the TOML config into this file instead of printing it
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Drop the `null` options which can not be expressed in TOML, so the
+        /// values of the preset apply instead
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+const JS_CONFIG_FILES: [&str; 2] = [".zhlintrc", ".zhlintrc.json"];
+
+/// Find the first existing file of the candidates.
+fn find_file(candidates: &[&str]) -> Option<PathBuf> {
+    candidates
+        .iter()
+        .map(PathBuf::from)
+        .find(|path| path.is_file())
 }
 
 fn main() {
     let args = Args::parse();
 
-    let config: Config = match fs::read_to_string(args.config) {
-        Ok(config_file) => {
-            toml::from_str(&config_file).expect("Config file is not a legal TOML file.")
+    match &args.command {
+        Some(Command::Config(ConfigCommand::Check)) => {
+            match args.config.clone().or_else(|| find_file(&CONFIG_FILES)) {
+                Some(path) => check_config(&path),
+                None => {
+                    println!("Unable to find config file.");
+                    exit(1);
+                }
+            }
+        }
+        Some(Command::Config(ConfigCommand::Print { file })) => {
+            print_config(with_file_type(load_config_layers(&args, file), file), file)
+        }
+        Some(Command::Config(ConfigCommand::Schema)) => println!("{}", Config::json_schema()),
+        Some(Command::Config(ConfigCommand::Migrate {
+            input,
+            output,
+            force,
+        })) => migrate_config(
+            input.clone().or_else(|| find_file(&JS_CONFIG_FILES)),
+            output.clone(),
+            *force,
+        ),
+        None => format_files(&args),
+    }
+}
+
+/// The config files applying to `path`, the given config file or the ones of
+/// the directory of `path` and its parents.
+fn config_files(args: &Args, path: &Path) -> Vec<PathBuf> {
+    match &args.config {
+        Some(config) => vec![config.clone()],
+        None => find_config_files(path),
+    }
+}
+
+/// Load the config files of `path` and the command line overrides as layers,
+/// a missing file means the default config.
+fn load_config_layers(args: &Args, path: &Path) -> ConfigLayers {
+    let mut layers = ConfigLayers::new();
+    for path in config_files(args, path).iter().filter(|path| path.exists()) {
+        if let Err(e) = layers.push_file(path) {
+            println!("Invalid config file: {e}");
+            exit(1);
         }
+    }
+    if let Err(e) = layers.push_args(&args.rules, &args.ignores) {
+        println!("Invalid argument: {e}");
+        exit(1);
+    }
+    for warning in layers.warnings() {
+        eprintln!("Warning: {warning}");
+    }
+    layers
+}
+
+fn check_config(path: &Path) {
+    match Config::from_file(path) {
+        Ok(_) => println!("Config file is valid: {}", path.display()),
+        Err(e) => {
+            println!("Invalid config file: {e}");
+            exit(1);
+        }
+    }
+}
+
+fn migrate_config(input: Option<PathBuf>, output: Option<PathBuf>, force: bool) {
+    let Some(input) = input else {
+        println!("Unable to find JSON config file.");
+        exit(1);
+    };
+    let js_config = match fs::read_to_string(&input) {
+        Ok(s) => JsConfig::parse(&s),
         Err(e) => {
-            println!("Unable to read config file, using the default config: {e}");
-            Config::default()
+            println!("Unable to read config file: {e}");
+            exit(1);
         }
     };
+    let js_config = js_config.unwrap_or_else(|e| {
+        println!("Invalid config file: {}: {e}", input.display());
+        exit(1);
+    });
+    let (toml, dropped) = js_config.to_toml();
+    for warning in js_config.warnings {
+        eprintln!("Warning: {warning}");
+    }
+    if !dropped.is_empty() && !force {
+        for key in dropped {
+            println!("`{key}` is null, which can not be expressed in TOML");
+        }
+        println!("Use --force to drop them, so the values of the preset apply instead.");
+        exit(1);
+    }
+    for key in dropped {
+        eprintln!("Warning: `{key}` is null, which can not be expressed in TOML, ignored");
+    }
+    match output {
+        Some(output) => {
+            if let Err(e) = fs::write(&output, toml) {
+                println!("Unable to write file: {e}");
+                exit(1);
+            }
+        }
+        None => print!("{toml}"),
+    }
+}
 
+fn print_config(layers: ConfigLayers, file: &Path) {
+    let file_content = match fs::read_to_string(file) {
+        Ok(s) => s,
+        Err(e) => {
+            println!("Unable to read file: {e}");
+            exit(1);
+        }
+    };
+    match layers
+        .with_front_matter(&file_content)
+        .and_then(|layers| layers.to_annotated_toml())
+    {
+        Ok(s) => print!("{s}"),
+        Err(e) => {
+            println!("Invalid config: {e}");
+            exit(1);
+        }
+    }
+}
+
+/// `.mdx` files are parsed as MDX, unless it is configured, the empty table
+/// does not change the configured one.
+fn with_file_type(mut layers: ConfigLayers, path: &Path) -> ConfigLayers {
+    if path.extension().is_some_and(|x| x == "mdx") {
+        layers
+            .push_toml(ConfigSource::FileType, "mdx = {}")
+            .unwrap();
+    }
+    layers
+}
+
+/// Load the config layers of a file, the files in the same directory share
+/// the config files.
+fn load_config(
+    args: &Args,
+    configs: &mut HashMap<PathBuf, ConfigLayers>,
+    path: &Path,
+) -> ConfigLayers {
+    let dir = path
+        .parent()
+        .filter(|x| !x.as_os_str().is_empty())
+        .unwrap_or(Path::new("."))
+        .to_path_buf();
+    let layers = configs.entry(dir).or_insert_with_key(|dir| {
+        if args.config.is_none() && config_files(args, path).is_empty() {
+            println!(
+                "Unable to find config file for {}, using the default config.",
+                dir.display()
+            );
+        }
+        let layers = load_config_layers(args, path);
+        if let Err(e) = layers.resolve() {
+            println!("Invalid config: {e}");
+            exit(1);
+        }
+        layers
+    });
+    with_file_type(layers.clone(), path)
+}
+
+fn format_files(args: &Args) {
+    if args.config.as_deref().is_some_and(|x| !x.exists()) {
+        println!("Unable to read config file, using the default config.");
+    }
+    let mut paths = Vec::new();
+    let mut configs = HashMap::new();
+    let mut anchor_changes = HashMap::new();
     for path in glob(&args.path).expect("Path pattern error.") {
         match path {
             Ok(path) => {
@@ -48,13 +278,28 @@ fn main() {
                     }
                 };
 
+                let layers = load_config(args, &mut configs, &path);
                 let mut res: String = String::new();
-                run(&file_content, &config, &mut res).unwrap();
+                let report = match run_with_layers(&file_content, &layers, &mut res) {
+                    Ok(report) => report,
+                    Err(e) => {
+                        println!("Unable to format file: {e}");
+                        exit(1);
+                    }
+                };
+                print_diagnostics(&path, &file_content, &report.diagnostics);
 
                 if let Err(e) = fs::write(&path, res) {
                     println!("Unable to write file: {}", e);
                     exit(1);
                 }
+
+                if let (false, Ok(canonical)) =
+                    (report.anchor_changes.is_empty(), path.canonicalize())
+                {
+                    anchor_changes.insert(canonical, report.anchor_changes);
+                }
+                paths.push(path);
             }
             Err(e) => {
                 println!("Can not read file: {e}");
@@ -62,4 +307,100 @@ fn main() {
             }
         }
     }
+
+    if !anchor_changes.is_empty() {
+        update_anchor_links(&paths, args, &mut configs, &anchor_changes);
+    }
+}
+
+/// Update the links to the anchors changed in other files, after all the files
+/// are formatted.
+fn update_anchor_links(
+    paths: &[PathBuf],
+    args: &Args,
+    configs: &mut HashMap<PathBuf, ConfigLayers>,
+    anchor_changes: &HashMap<PathBuf, Vec<AnchorChange>>,
+) {
+    for path in paths {
+        let file_content = match fs::read_to_string(path) {
+            Ok(s) => s,
+            Err(e) => {
+                println!("Unable to read file: {e}");
+                exit(1);
+            }
+        };
+        let config = load_config(args, configs, path)
+            .with_front_matter(&file_content)
+            .and_then(|layers| layers.resolve())
+            .unwrap_or_else(|e| {
+                println!("Invalid config: {e}");
+                exit(1);
+            });
+        let dir = path.parent().unwrap_or(Path::new(""));
+        // `guide.html` and `guide` are also links to `guide.md`
+        let (res, diagnostics) = update_fragment_links(&file_content, &config, |target| {
+            let target = dir.join(target);
+            [target.clone(), target.with_extension("md")]
+                .iter()
+                .filter_map(|x| x.canonicalize().ok())
+                .find_map(|x| anchor_changes.get(&x))
+                .map(Vec::as_slice)
+        });
+        print_diagnostics(path, &file_content, &diagnostics);
+
+        if res != file_content {
+            if let Err(e) = fs::write(path, res) {
+                println!("Unable to write file: {}", e);
+                exit(1);
+            }
+        }
+    }
+}
+
+fn print_diagnostics(path: &Path, text: &str, diagnostics: &[Diagnostic]) {
+    for diagnostic in diagnostics {
+        let (line, column) = diagnostic.position(text);
+        eprintln!("{}:{line}:{column}: {}", path.display(), diagnostic.message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_global_args() {
+        for args in [
+            vec![
+                "zhlint",
+                "--rule",
+                "trim_space=true",
+                "config",
+                "print",
+                "a.md",
+            ],
+            vec![
+                "zhlint",
+                "config",
+                "print",
+                "a.md",
+                "--rule",
+                "trim_space=true",
+            ],
+        ] {
+            let args = Args::try_parse_from(args).unwrap();
+            assert!(matches!(
+                args.command,
+                Some(Command::Config(ConfigCommand::Print { file })) if file == Path::new("a.md")
+            ));
+            assert_eq!(args.rules, vec!["trim_space=true"]);
+        }
+
+        let args = Args::try_parse_from(["zhlint", "--ignore", "foo", "docs/*.md"]).unwrap();
+        assert!(args.command.is_none());
+        assert_eq!(args.path, "docs/*.md");
+        assert_eq!(args.ignores, vec!["foo"]);
+        let args = Args::try_parse_from(["zhlint"]).unwrap();
+        assert_eq!(args.path, "./**/*.md");
+    }
 }
@@ -0,0 +1,49 @@
+//! Math spans, inline `$O(n)$` and display `$$...$$`, are not parsed by
+//! pulldown-cmark, so they are found in the source and replaced with raw
+//! events by [`protect_ranges`]. The ranges of the math events are kept in
+//! [`Context`], so they are not taken for other raw events like HTML.
+
+use std::ops::Range;
+
+use pulldown_cmark::Event;
+use regex::Regex;
+
+use crate::{protect::protect_ranges, Context};
+
+const MATH_RE: &str = r"\$\$(?s:.+?)\$\$|\$[^\s$](?:[^$\n]*?[^\s$\\])?\$";
+
+/// Whether the event of the range is a math span made by [`protect_math`].
+pub fn is_math(range: Option<&Range<usize>>, ctx: &Context) -> bool {
+    range.is_some_and(|x| ctx.math_ranges.contains(x))
+}
+
+/// Find the math spans in the source. Like Pandoc, the `$` of inline math
+/// can not be escaped, and the closing `$` can not be followed by a digit,
+/// so that `$5 and $10` is not math.
+fn get_math_ranges(text: &str) -> Vec<Range<usize>> {
+    Regex::new(MATH_RE)
+        .unwrap()
+        .find_iter(text)
+        .map(|x| x.range())
+        .filter(|x| !text[..x.start].ends_with('\\'))
+        .filter(|x| !text[x.end..].starts_with(|c: char| c.is_ascii_digit()))
+        .collect()
+}
+
+/// Replace the events of math spans with raw events.
+pub(crate) fn protect_math<'a>(
+    events: Vec<(Event<'a>, Range<usize>)>,
+    text: &str,
+) -> Vec<(Event<'a>, Range<usize>)> {
+    protect_ranges(events, text, get_math_ranges(text))
+}
+
+/// The ranges of the raw events made by [`protect_math`].
+pub(crate) fn get_math_events(events: &[(Event, Range<usize>)], text: &str) -> Vec<Range<usize>> {
+    let ranges = get_math_ranges(text);
+    events
+        .iter()
+        .filter(|(event, range)| matches!(event, Event::Html(_)) && ranges.contains(range))
+        .map(|(_, range)| range.clone())
+        .collect()
+}
@@ -0,0 +1,315 @@
+//! MDX, where ESM and JSX are mixed with markdown.
+//!
+//! - `import` and `export` paragraphs are kept as they are
+//! - expressions like `{props.name}` are kept as they are
+//! - JSX elements are parsed as HTML, the tags are kept as they are except the
+//!   string values of `attributes`, e.g. `label` of `<TabItem label="示例">`,
+//!   and the prose children of elements are formatted
+//! - inline JSX tags in paragraphs, e.g. `<Badge text="新" />`, are kept as
+//!   they are, the text around is formatted as markdown
+
+use std::ops::Range;
+
+use pulldown_cmark::{Event, Tag};
+
+use crate::{
+    config::{Config, Mdx},
+    parser::is_inline,
+    protect::protect_ranges,
+};
+
+const ESM_KEYWORDS: [&str; 2] = ["import ", "export "];
+/// Elements whose children are not prose, besides `code_like_html_tags`.
+const RAW_ELEMENTS: [&str; 3] = ["pre", "script", "style"];
+
+/// Replace ESM and expressions with raw events, and format JSX elements.
+pub(crate) fn protect_mdx<'a>(
+    events: Vec<(Event<'a>, Range<usize>)>,
+    text: &str,
+    config: &Config,
+    lint: impl Fn(&str) -> String,
+) -> Vec<(Event<'a>, Range<usize>)> {
+    let Some(mdx) = &config.mdx else {
+        return events;
+    };
+    let events = protect_esm(events, text);
+    let ranges = get_expression_ranges(&events, text);
+    let events = protect_ranges(events, text, ranges);
+    // the depth of blocks with inline content, where JSX tags are inline
+    let mut inline_depth = 0;
+    merge_html(events)
+        .into_iter()
+        .map(|(event, range)| match event {
+            Event::Start(Tag::Paragraph | Tag::Heading(..) | Tag::TableCell) => {
+                inline_depth += 1;
+                (event, range)
+            }
+            Event::End(Tag::Paragraph | Tag::Heading(..) | Tag::TableCell) => {
+                inline_depth -= 1;
+                (event, range)
+            }
+            Event::Html(s) if inline_depth == 0 && s.starts_with('<') => {
+                (Event::Html(lint_jsx(&s, mdx, config, &lint).into()), range)
+            }
+            event => (event, range),
+        })
+        .collect()
+}
+
+/// Replace the content of top-level paragraphs starting with `import` or
+/// `export` with raw events.
+fn protect_esm<'a>(
+    events: Vec<(Event<'a>, Range<usize>)>,
+    text: &str,
+) -> Vec<(Event<'a>, Range<usize>)> {
+    let mut res = Vec::with_capacity(events.len());
+    let mut depth = 0;
+    let mut events = events.into_iter().peekable();
+    while let Some((event, range)) = events.next() {
+        let is_esm = depth == 0
+            && matches!(event, Event::Start(Tag::Paragraph))
+            && ESM_KEYWORDS
+                .iter()
+                .any(|x| text[range.clone()].starts_with(x));
+        match &event {
+            Event::Start(tag) if !is_inline(tag) => depth += 1,
+            Event::End(tag) if !is_inline(tag) => depth -= 1,
+            _ => (),
+        }
+        res.push((event, range));
+        if !is_esm {
+            continue;
+        }
+
+        let mut content: Option<Range<usize>> = None;
+        while let Some((_, range)) = events.next_if(|(x, _)| *x != Event::End(Tag::Paragraph)) {
+            content = Some(content.map_or(range.clone(), |x| x.start..range.end));
+        }
+        if let Some(content) = content {
+            res.push((
+                Event::Html(text[content.clone()].to_string().into()),
+                content,
+            ));
+        }
+    }
+    res
+}
+
+/// Find the expressions like `{props.name}` in paragraphs and headings.
+fn get_expression_ranges(events: &[(Event, Range<usize>)], text: &str) -> Vec<Range<usize>> {
+    let mut res = Vec::new();
+    for (event, range) in events {
+        if !matches!(event, Event::Start(Tag::Paragraph | Tag::Heading(..))) {
+            continue;
+        }
+        let s = &text[range.clone()];
+        let mut i = 0;
+        while let Some(start) = s[i..].find('{').map(|x| i + x) {
+            i = start + 1;
+            if s[..start].ends_with('\\') {
+                continue;
+            }
+            if let Some(end) = find_closing_brace(&s[start..]) {
+                res.push(range.start + start..range.start + start + end);
+                i = start + end;
+            }
+        }
+    }
+    res
+}
+
+/// The end of a balanced expression at the start of `s`, after the `}`.
+fn find_closing_brace(s: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote = None;
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => (),
+            (None, '"' | '\'' | '`') => quote = Some(c),
+            (None, '{') => depth += 1,
+            (None, '}') => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Merge adjacent raw events, a JSX tag may take several lines of an HTML
+/// block.
+fn merge_html(events: Vec<(Event, Range<usize>)>) -> Vec<(Event, Range<usize>)> {
+    let mut res: Vec<(Event, Range<usize>)> = Vec::with_capacity(events.len());
+    for (event, range) in events {
+        match (res.last_mut(), &event) {
+            (Some((Event::Html(prev), prev_range)), Event::Html(s))
+                if prev_range.end == range.start && prev.ends_with('\n') =>
+            {
+                *prev = format!("{prev}{s}").into();
+                prev_range.end = range.end;
+            }
+            _ => res.push((event, range)),
+        }
+    }
+    res
+}
+
+/// Format the attributes in `attributes` and the prose children of JSX.
+fn lint_jsx(s: &str, mdx: &Mdx, config: &Config, lint: &impl Fn(&str) -> String) -> String {
+    let mut res = String::with_capacity(s.len());
+    let mut raw_depth: usize = 0;
+    let mut i = 0;
+    while i < s.len() {
+        let rest = &s[i..];
+        if rest.starts_with("<!--") {
+            let end = rest.find("-->").map_or(rest.len(), |x| x + 3);
+            res.push_str(&rest[..end]);
+            i += end;
+        } else if rest.starts_with('<') {
+            let end = find_tag_end(rest);
+            let tag = &rest[..end];
+            let name: String = tag
+                .trim_start_matches(['<', '/'])
+                .chars()
+                .take_while(|c| c.is_alphanumeric() || matches!(c, '.' | '-' | ':' | '_'))
+                .collect();
+            let is_raw = RAW_ELEMENTS.contains(&name.as_str())
+                || config
+                    .rules
+                    .code_like_html_tags
+                    .iter()
+                    .any(|x| x.eq_ignore_ascii_case(&name));
+            if is_raw && !tag.ends_with("/>") {
+                if tag.starts_with("</") {
+                    raw_depth = raw_depth.saturating_sub(1);
+                } else {
+                    raw_depth += 1;
+                }
+            }
+            res.push_str(&lint_attributes(tag, mdx, lint));
+            i += end;
+        } else if rest.starts_with('{') {
+            let end = find_closing_brace(rest).unwrap_or(rest.len());
+            res.push_str(&rest[..end]);
+            i += end;
+        } else {
+            let end = rest.find(['<', '{']).unwrap_or(rest.len());
+            if raw_depth > 0 {
+                res.push_str(&rest[..end]);
+            } else {
+                res.push_str(&lint_lines(&rest[..end], lint));
+            }
+            i += end;
+        }
+    }
+    res
+}
+
+/// The end of the tag at the start of `s`, after the `>`.
+fn find_tag_end(s: &str) -> usize {
+    let mut quote = None;
+    let mut depth = 0;
+    let mut chars = s.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match (quote, c) {
+            (Some(_), '\\') => {
+                chars.next();
+            }
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => (),
+            (None, '"' | '\'') if depth == 0 => quote = Some(c),
+            (None, '{') => depth += 1,
+            (None, '}') => depth -= 1,
+            (None, '>') if depth == 0 => return i + 1,
+            _ => (),
+        }
+    }
+    s.len()
+}
+
+/// Format the quoted values of the attributes in `attributes`.
+fn lint_attributes(tag: &str, mdx: &Mdx, lint: &impl Fn(&str) -> String) -> String {
+    let mut res = String::with_capacity(tag.len());
+    let mut name = "";
+    let mut i = 0;
+    while i < tag.len() {
+        let rest = &tag[i..];
+        let c = rest.chars().next().unwrap();
+        match c {
+            '"' | '\'' => {
+                let Some(len) = find_quote(&rest[1..], c) else {
+                    res.push_str(rest);
+                    break;
+                };
+                let value = &rest[1..1 + len];
+                res.push(c);
+                if mdx.attributes.iter().any(|x| x == name) {
+                    let escaped = format!("\\{c}");
+                    let linted = lint(&value.replace(&escaped, &c.to_string()));
+                    res.push_str(&linted.replace(c, &escaped));
+                } else {
+                    res.push_str(value);
+                }
+                res.push(c);
+                name = "";
+                i += len + 2;
+            }
+            '{' => {
+                let end = find_closing_brace(rest).unwrap_or(rest.len());
+                res.push_str(&rest[..end]);
+                i += end;
+            }
+            c if c.is_alphanumeric() || matches!(c, '-' | '_' | ':') => {
+                let end = rest
+                    .find(|c: char| !(c.is_alphanumeric() || matches!(c, '-' | '_' | ':' | '.')))
+                    .unwrap_or(rest.len());
+                name = &rest[..end];
+                res.push_str(name);
+                i += end;
+            }
+            c => {
+                if !c.is_whitespace() && c != '=' {
+                    name = "";
+                }
+                res.push(c);
+                i += c.len_utf8();
+            }
+        }
+    }
+    res
+}
+
+/// Find the closing quote, the quotes escaped by `\` are skipped, e.g.
+/// `title="a\"b"`.
+fn find_quote(s: &str, quote: char) -> Option<usize> {
+    let mut chars = s.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            c if c == quote => return Some(i),
+            _ => (),
+        }
+    }
+    None
+}
+
+/// Format each line of the text, the spaces around are kept.
+fn lint_lines(s: &str, lint: &impl Fn(&str) -> String) -> String {
+    s.split_inclusive('\n')
+        .map(|line| {
+            let content = line.trim();
+            if content.is_empty() {
+                return line.to_string();
+            }
+            let start = line.len() - line.trim_start().len();
+            let end = start + content.len();
+            format!("{}{}{}", &line[..start], lint(content), &line[end..])
+        })
+        .collect()
+}
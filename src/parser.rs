@@ -1,50 +1,124 @@
 use std::ops::Range;
 
-use pulldown_cmark::{Event, OffsetIter};
+use pulldown_cmark::{Event, Tag};
 
-use crate::char_kind::{CharKind, CharKindTrait};
+use crate::{
+    char_kind::{CharKind, CharKindTrait},
+    html::HtmlTag,
+};
 
 pub struct EventCursor<'a> {
-    events: OffsetIter<'a, 'a>,
+    events: Vec<(Event<'a>, Range<usize>)>,
+    index: usize,
     pub prev_event: Option<(Event<'a>, Range<usize>)>,
     pub current_event: Option<(Event<'a>, Range<usize>)>,
     pub next_event: Option<(Event<'a>, Range<usize>)>,
 }
 
 impl<'a> EventCursor<'a> {
-    pub fn new(mut events: OffsetIter<'a, 'a>) -> Self {
-        Self {
+    pub fn new<I: IntoIterator<Item = (Event<'a>, Range<usize>)>>(events: I) -> Self {
+        let mut res = Self {
+            events: events.into_iter().collect(),
+            index: 0,
             prev_event: None,
-            current_event: events.next(),
-            next_event: events.next(),
-            events,
-        }
+            current_event: None,
+            next_event: None,
+        };
+        res.update();
+        res
     }
 
     pub fn advance(&mut self) {
-        self.prev_event = self.current_event.clone();
-        self.current_event = self.next_event.clone();
-        self.next_event = self.events.next();
+        self.index += 1;
+        self.update();
     }
 
-    pub fn to_text_cursor(&self) -> Option<TextCursor> {
+    fn update(&mut self) {
+        let get = |i: Option<usize>| i.and_then(|i| self.events.get(i)).cloned();
+        self.prev_event = get(self.index.checked_sub(1));
+        self.current_event = get(Some(self.index));
+        self.next_event = get(Some(self.index + 1));
+    }
+
+    /// The nearest event before the current one, skipping inline markups.
+    fn prev_through(&self) -> Option<(Event<'a>, Range<usize>)> {
+        self.events[..self.index]
+            .iter()
+            .rev()
+            .find(|(event, _)| !is_transparent(event))
+            .cloned()
+    }
+
+    /// The nearest event after the current one, skipping inline markups.
+    fn next_through(&self) -> Option<(Event<'a>, Range<usize>)> {
+        self.events
+            .get(self.index + 1..)?
+            .iter()
+            .find(|(event, _)| !is_transparent(event))
+            .cloned()
+    }
+
+    pub fn to_text_cursor(&self) -> Option<TextCursor<'_>> {
         if let Some((Event::Text(s), r)) = &self.current_event {
-            Some(TextCursor {
-                chars: s
-                    .chars()
-                    .enumerate()
-                    .map(|(i, c)| (c, Some(r.start + i)))
-                    .collect(),
-                index: 0,
-                prev_event: self.prev_event.clone(),
-                next_event: self.next_event.clone(),
-            })
+            let mut text_cursor = TextCursor::new(s, r.start);
+            text_cursor.prev_event = self.prev_event.clone();
+            text_cursor.next_event = self.next_event.clone();
+            text_cursor.prev_through = self.prev_through();
+            text_cursor.next_through = self.next_through();
+            Some(text_cursor)
         } else {
             None
         }
     }
 }
 
+/// Inline markups which the rules see through, so that `*foo*文字` is
+/// checked like `foo文字`. Inline HTML tags like `<span>` are seen through too.
+pub(crate) fn is_transparent(event: &Event) -> bool {
+    match event {
+        Event::Start(Tag::Emphasis | Tag::Strong | Tag::Strikethrough | Tag::Link(..))
+        | Event::End(Tag::Emphasis | Tag::Strong | Tag::Strikethrough | Tag::Link(..)) => true,
+        Event::Html(s) => HtmlTag::parse(s).is_some_and(|tag| tag.is_wrapper()),
+        _ => false,
+    }
+}
+
+/// Whether the event opens a transparent markup, e.g. `Start(Emphasis)` or
+/// `<span>`.
+fn is_opening(event: &Event) -> bool {
+    match event {
+        Event::Start(_) => is_transparent(event),
+        Event::Html(s) => HtmlTag::parse(s).is_some_and(|tag| tag.is_wrapper() && !tag.closing),
+        _ => false,
+    }
+}
+
+/// Whether the event closes a transparent markup, see [`is_opening`].
+fn is_closing(event: &Event) -> bool {
+    match event {
+        Event::End(_) => is_transparent(event),
+        Event::Html(s) => HtmlTag::parse(s).is_some_and(|tag| tag.is_wrapper() && tag.closing),
+        _ => false,
+    }
+}
+
+/// Inline tags, which can be inside a paragraph.
+pub(crate) fn is_inline(tag: &Tag) -> bool {
+    matches!(
+        tag,
+        Tag::Emphasis | Tag::Strong | Tag::Strikethrough | Tag::Link(..) | Tag::Image(..)
+    )
+}
+
+/// Markups whose delimiters are parsed by the flanking rules.
+fn is_delimiter(event: &Event) -> bool {
+    matches!(
+        event,
+        Event::Start(Tag::Emphasis | Tag::Strong | Tag::Strikethrough)
+            | Event::End(Tag::Emphasis | Tag::Strong | Tag::Strikethrough)
+    )
+}
+
 #[derive(Debug, PartialEq)]
 pub enum Token<'a> {
     None,
@@ -82,11 +156,40 @@ impl<'a> From<(char, Option<usize>)> for Token<'a> {
 pub struct TextCursor<'a> {
     chars: Vec<(char, Option<usize>)>,
     index: usize,
+    /// The events next to the text.
     prev_event: Option<(Event<'a>, Range<usize>)>,
     next_event: Option<(Event<'a>, Range<usize>)>,
+    /// The nearest events which are not inline markups.
+    prev_through: Option<(Event<'a>, Range<usize>)>,
+    next_through: Option<(Event<'a>, Range<usize>)>,
 }
 
 impl TextCursor<'_> {
+    /// Create a text cursor without surrounding events, `offset` is the offset
+    /// of the text in the source.
+    pub fn new(text: &str, offset: usize) -> Self {
+        Self {
+            chars: text
+                .char_indices()
+                .map(|(i, c)| (c, Some(offset + i)))
+                .collect(),
+            index: 0,
+            prev_event: None,
+            next_event: None,
+            prev_through: None,
+            next_through: None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chars.is_empty()
+    }
+
+    /// Whether the cursor has been moved past the last char.
+    pub fn is_end(&self) -> bool {
+        self.index >= self.chars.len()
+    }
+
     pub fn advance(&mut self) -> bool {
         self.index += 1;
         self.index < self.chars.len()
@@ -99,7 +202,7 @@ impl TextCursor<'_> {
                 .all(|(i, c)| self.chars.get(self.index + i).is_some_and(|x| x.0 == c))
             {
                 self.index += s.len();
-                if self.current().is_whitespace() {
+                if self.index < self.chars.len() && self.current().is_whitespace() {
                     self.index += 1;
                 }
                 break;
@@ -107,19 +210,55 @@ impl TextCursor<'_> {
         }
     }
 
-    pub fn prev(&self) -> Token {
+    /// The previous char, or the previous event at the start of the text.
+    /// Inline markups like `*` or `[` are seen through, so the last char of
+    /// the previous text is returned instead.
+    pub fn prev(&self) -> Token<'_> {
         if self.index == 0 {
-            self.prev_event.as_ref().into()
+            match &self.prev_through {
+                Some((Event::Text(s), _)) if !s.is_empty() => {
+                    Token::Char(s.chars().last().unwrap())
+                }
+                event => event.as_ref().into(),
+            }
         } else {
             Token::Char(self.chars[self.index - 1].0)
         }
     }
 
-    pub fn prev_skip_space(&self) -> Token {
+    /// The range of the event returned by [`TextCursor::prev`], if it is an
+    /// event.
+    pub fn prev_range(&self) -> Option<&Range<usize>> {
+        match &self.prev_through {
+            Some((Event::Text(s), _)) if !s.is_empty() => None,
+            event if self.index == 0 => event.as_ref().map(|x| &x.1),
+            _ => None,
+        }
+    }
+
+    /// The event before the text, if the cursor is at the start of the text.
+    pub fn prev_event(&self) -> Token<'_> {
+        if self.index == 0 {
+            self.prev_event.as_ref().into()
+        } else {
+            Token::None
+        }
+    }
+
+    /// The previous char which is not a space, inline markups are seen
+    /// through like [`TextCursor::prev`].
+    pub fn prev_skip_space(&self) -> Token<'_> {
         let mut i = self.index;
         loop {
             if i == 0 {
-                break Token::None;
+                break match &self.prev_through {
+                    Some((Event::Text(s), _)) => s
+                        .chars()
+                        .rev()
+                        .find(|c| c.kind() != CharKind::Space)
+                        .map_or(Token::None, Token::Char),
+                    event => event.as_ref().into(),
+                };
             }
             i -= 1;
             if self.chars[i].0.kind() != CharKind::Space {
@@ -128,28 +267,62 @@ impl TextCursor<'_> {
         }
     }
 
+    /// The current char, or `\0` if the whole text has been deleted.
     pub fn current(&self) -> char {
-        self.chars[self.index].0
+        self.chars.get(self.index).map_or('\0', |x| x.0)
     }
 
     pub fn current_offset(&self) -> Option<usize> {
-        self.chars[self.index].1
+        self.chars.get(self.index).and_then(|x| x.1)
     }
 
-    pub fn next(&self) -> Token {
-        if self.index >= self.chars.len() - 1 {
-            self.next_event.as_ref().into()
+    /// The next char, or the next event at the end of the text. Inline
+    /// markups are seen through like [`TextCursor::prev`].
+    pub fn next(&self) -> Token<'_> {
+        if self.index + 1 >= self.chars.len() {
+            match &self.next_through {
+                Some((Event::Text(s), _)) if !s.is_empty() => {
+                    Token::Char(s.chars().next().unwrap())
+                }
+                event => event.as_ref().into(),
+            }
         } else {
             Token::Char(self.chars[self.index + 1].0)
         }
     }
 
-    pub fn next_skip_space(&self) -> Token {
+    /// The range of the event returned by [`TextCursor::next`], see
+    /// [`TextCursor::prev_range`].
+    pub fn next_range(&self) -> Option<&Range<usize>> {
+        match &self.next_through {
+            Some((Event::Text(s), _)) if !s.is_empty() => None,
+            event if self.index + 1 >= self.chars.len() => event.as_ref().map(|x| &x.1),
+            _ => None,
+        }
+    }
+
+    /// The event after the text, if the cursor is at the end of the text.
+    pub fn next_event(&self) -> Token<'_> {
+        if self.index + 1 >= self.chars.len() {
+            self.next_event.as_ref().into()
+        } else {
+            Token::None
+        }
+    }
+
+    /// The next char which is not a space, see [`TextCursor::prev_skip_space`].
+    pub fn next_skip_space(&self) -> Token<'_> {
         let mut i = self.index;
         loop {
             i += 1;
-            if i == self.chars.len() {
-                break Token::None;
+            if i >= self.chars.len() {
+                break match &self.next_through {
+                    Some((Event::Text(s), _)) => s
+                        .chars()
+                        .find(|c| c.kind() != CharKind::Space)
+                        .map_or(Token::None, Token::Char),
+                    event => event.as_ref().into(),
+                };
             }
             if self.chars[i].0.kind() != CharKind::Space {
                 break self.chars[i].0.into();
@@ -157,7 +330,12 @@ impl TextCursor<'_> {
         }
     }
 
+    /// Delete the current char. A space next to the delimiters like `**` is
+    /// kept if deleting it would break the emphasis, e.g. `**“重要”** 之后`.
     pub fn delete(&mut self) {
+        if self.breaks_delimiter() {
+            return;
+        }
         self.chars.remove(self.index);
         if self.index > 0 {
             self.index -= 1;
@@ -168,13 +346,60 @@ impl TextCursor<'_> {
         self.chars[self.index].0 = c;
     }
 
+    /// Add a char before the current one. At the start of the text inside an
+    /// inline markup, nothing is added, because the char would be inside the
+    /// markup, and the text before will add it outside.
     pub fn add_prev(&mut self, c: char) {
+        if self.index == 0 && self.prev_event.as_ref().is_some_and(|(e, _)| is_opening(e)) {
+            return;
+        }
         self.chars.insert(self.index, (c, None));
     }
 
+    /// Add a char after the current one, see [`TextCursor::add_prev`].
     pub fn add_next(&mut self, c: char) {
+        if self.index + 1 == self.chars.len()
+            && self.next_event.as_ref().is_some_and(|(e, _)| is_closing(e))
+        {
+            return;
+        }
         self.chars.insert(self.index + 1, (c, None));
     }
+
+    /// Whether deleting the current char, which is next to the delimiters,
+    /// makes them not flanking any more. By CommonMark, a closing delimiter
+    /// preceded by a punctuation must be followed by a space or punctuation,
+    /// and vice versa for an opening delimiter.
+    fn breaks_delimiter(&self) -> bool {
+        let is_letter =
+            |x: Token| x != Token::None && x.kind() != CharKind::Space && !x.is_punctuation();
+        if !self.current().is_whitespace() {
+            return false;
+        }
+        if self.index == 0
+            && self
+                .prev_event
+                .as_ref()
+                .is_some_and(|(e, _)| is_delimiter(e))
+        {
+            let after = self.chars.get(1).map(|x| Token::Char(x.0));
+            return self.prev().is_punctuation() && is_letter(after.unwrap_or_else(|| self.next()));
+        }
+        if self.index + 1 == self.chars.len()
+            && self
+                .next_event
+                .as_ref()
+                .is_some_and(|(e, _)| is_delimiter(e))
+        {
+            let before = self
+                .index
+                .checked_sub(1)
+                .map(|i| Token::Char(self.chars[i].0));
+            return self.next().is_punctuation()
+                && is_letter(before.unwrap_or_else(|| self.prev()));
+        }
+        false
+    }
 }
 
 impl From<TextCursor<'_>> for String {
@@ -187,6 +412,23 @@ impl From<TextCursor<'_>> for String {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_edit_at_edges() {
+        let strong = (Event::Start(Tag::Strong), 0..0);
+        let mut cursor = TextCursor::new("文字", 0);
+        cursor.prev_event = Some(strong.clone());
+        cursor.add_prev(' ');
+        cursor.add_next(' ');
+        assert_eq!(String::from(cursor), "文 字");
+
+        let mut cursor = TextCursor::new(" foo", 0);
+        cursor.prev_event = Some(strong.clone());
+        cursor.prev_through = Some((Event::Text("“重要”".into()), 0..0));
+        cursor.delete();
+        assert_eq!(cursor.prev_skip_space(), Token::Char('”'));
+        assert_eq!(String::from(cursor), " foo");
+    }
+
     #[test]
     fn test() {
         use pulldown_cmark::Parser;
@@ -0,0 +1,114 @@
+//! Spans of the source which are not markdown, e.g. math or template tags,
+//! are replaced with raw events, which are written back as they are and never
+//! touched by the rules.
+
+use std::ops::Range;
+
+use pulldown_cmark::{Event, Tag};
+
+use crate::parser::is_inline;
+
+/// The part of a text event before or after `offset`, if the offset of the
+/// text can be mapped to the source.
+fn split_text<'a>(
+    (event, range): &(Event<'a>, Range<usize>),
+    text: &str,
+    offset: usize,
+) -> Option<(String, String)> {
+    match event {
+        Event::Text(s)
+            if (range.start..=range.end).contains(&offset)
+                && &text[range.clone()] == s.as_ref() =>
+        {
+            let (before, after) = s.split_at(offset - range.start);
+            Some((before.to_string(), after.to_string()))
+        }
+        _ => None,
+    }
+}
+
+/// Replace the events of each range with a raw event of the source. A range
+/// is skipped if it is not inside one paragraph, or it is inside a code block
+/// or inline code.
+pub(crate) fn protect_ranges<'a>(
+    mut events: Vec<(Event<'a>, Range<usize>)>,
+    text: &str,
+    ranges: Vec<Range<usize>>,
+) -> Vec<(Event<'a>, Range<usize>)> {
+    for span in merge_ranges(ranges).into_iter().rev() {
+        let Some(first) = events.iter().position(|(event, range)| {
+            matches!(event, Event::Text(_)) && range.contains(&span.start)
+        }) else {
+            continue;
+        };
+        let Some(last) = events[first..]
+            .iter()
+            .position(|(event, range)| {
+                matches!(event, Event::Text(_)) && range.contains(&(span.end - 1))
+            })
+            .map(|x| first + x)
+        else {
+            continue;
+        };
+
+        let in_code_block = events[..first]
+            .iter()
+            .rev()
+            .find_map(|(event, _)| match event {
+                Event::Start(Tag::CodeBlock(_)) => Some(true),
+                Event::End(Tag::CodeBlock(_)) => Some(false),
+                _ => None,
+            })
+            .unwrap_or(false);
+        // the span must be inside one block, with balanced inline markups
+        let mut depth = 0;
+        let balanced = events[first..=last].iter().all(|(event, _)| {
+            match event {
+                Event::Start(tag) if is_inline(tag) => depth += 1,
+                Event::End(tag) if is_inline(tag) => depth -= 1,
+                Event::Text(_) | Event::SoftBreak | Event::HardBreak => (),
+                _ => return false,
+            }
+            depth >= 0
+        }) && depth == 0;
+        if in_code_block || !balanced {
+            continue;
+        }
+
+        let (Some((before, _)), Some((_, after))) = (
+            split_text(&events[first], text, span.start),
+            split_text(&events[last], text, span.end),
+        ) else {
+            continue;
+        };
+
+        let mut replacement = Vec::new();
+        let (first_range, last_range) = (events[first].1.clone(), events[last].1.clone());
+        if !before.is_empty() {
+            replacement.push((Event::Text(before.into()), first_range.start..span.start));
+        }
+        replacement.push((
+            Event::Html(text[span.clone()].to_string().into()),
+            span.clone(),
+        ));
+        if !after.is_empty() {
+            replacement.push((Event::Text(after.into()), span.end..last_range.end));
+        }
+        events.splice(first..=last, replacement);
+    }
+    events
+}
+
+/// Sort the ranges and merge the overlapping ones.
+fn merge_ranges(mut ranges: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    ranges.retain(|x| !x.is_empty());
+    ranges.sort_by_key(|x| x.start);
+    let mut res: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match res.last_mut() {
+            Some(last) if range.start < last.end => last.end = last.end.max(range.end),
+            _ => res.push(range),
+        }
+    }
+    res
+}
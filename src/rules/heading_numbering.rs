@@ -0,0 +1,78 @@
+//! This rule is checking the numbering of headings, the headings of the same
+//! level should be numbered in the same style, e.g. `一、` and `1.` are not
+//! mixed.
+//!
+//! Options
+//! - consistent_heading_numbering: bool
+//!   - `true`: report the headings numbered differently from the first
+//!     numbered heading of the same level
+//!   - `false`: do nothing (default)
+//!
+//! Details:
+//! - the styles are `一、`, `（一）`, `第一章`, `1.`, `1、`, `(1)` and `1)`
+//! - the headings without numbering are not checked
+//! - the problems are reported but not fixed, the numbers are not converted
+
+use std::{collections::HashMap, ops::Range};
+
+use pulldown_cmark::{Event, Tag};
+use regex::Regex;
+
+use crate::{config::Config, diagnostic::Diagnostic};
+
+/// The styles of numbering, and the regexes matching the start of headings.
+const NUMBERING_STYLES: [(&str, &str); 7] = [
+    ("一、", r"^[一二三四五六七八九十百零]+、"),
+    ("（一）", r"^[（(][一二三四五六七八九十百零]+[）)]"),
+    ("第一章", r"^第[一二三四五六七八九十百零0-9]+[章节篇部]"),
+    ("1.", r"^[0-9]+\.([0-9]+\.?)*(\s|$)"),
+    ("1、", r"^[0-9]+、"),
+    ("(1)", r"^[（(][0-9]+[）)]"),
+    ("1)", r"^[0-9]+[）)]"),
+];
+
+pub fn rule(events: &[(Event<'_>, Range<usize>)], config: &Config) -> Vec<Diagnostic> {
+    if !config.rules.consistent_heading_numbering {
+        return Vec::new();
+    }
+
+    let styles: Vec<(&str, Regex)> = NUMBERING_STYLES
+        .iter()
+        .map(|(style, re)| (*style, Regex::new(re).unwrap()))
+        .collect();
+    let mut first_styles = HashMap::new();
+    let mut res = Vec::new();
+    let mut heading = None;
+    for (event, range) in events {
+        match event {
+            Event::Start(Tag::Heading(level, ..)) => {
+                heading = Some((*level, range.start, String::new()))
+            }
+            Event::Text(s) | Event::Code(s) => {
+                if let Some((_, _, text)) = &mut heading {
+                    text.push_str(s);
+                }
+            }
+            Event::End(Tag::Heading(..)) => {
+                let Some((level, start, text)) = heading.take() else {
+                    continue;
+                };
+                let Some((style, _)) = styles.iter().find(|(_, re)| re.is_match(&text)) else {
+                    continue;
+                };
+                let first = *first_styles.entry(level).or_insert(*style);
+                if first != *style {
+                    res.push(Diagnostic::new(
+                        start,
+                        format!(
+                            "the numbering `{style}` is mixed with `{first}` in the headings of level {}",
+                            level as usize
+                        ),
+                    ));
+                }
+            }
+            _ => (),
+        }
+    }
+    res
+}
@@ -0,0 +1,70 @@
+//! This rule is removing the punctuations at the end of headings, which are
+//! titles but not sentences.
+//!
+//! Options
+//! - no_end_punctuation_in_heading: bool
+//!   - `true`: remove the punctuations like `。`, `：` and `.`
+//!   - `false`: do nothing, just keep the original format (default)
+//!
+//! Details:
+//! - `？` and `！` are kept, which change the meaning
+//! - inline markups at the end are seen through, e.g. `## **注意：**`
+//! - abbreviations in `skip_abbrs` and ellipses like `...` are kept
+//! - the punctuations in the ignored ranges are kept
+
+use std::ops::Range;
+
+use pulldown_cmark::{Event, Tag};
+
+use crate::{config::Config, ignore::is_ignored, parser::is_transparent};
+
+const END_PUNCTUATIONS: [char; 8] = ['。', '：', '，', '；', '.', ':', ',', ';'];
+
+// the signature of event rules
+#[allow(clippy::ptr_arg)]
+pub fn rule(
+    events: &mut Vec<(Event<'_>, Range<usize>)>,
+    config: &Config,
+    ignore_ranges: &[Range<usize>],
+) {
+    if !config.rules.no_end_punctuation_in_heading {
+        return;
+    }
+
+    for i in 1..events.len() {
+        if !matches!(events[i].0, Event::End(Tag::Heading(..))) {
+            continue;
+        }
+        let Some(last) = events[..i]
+            .iter()
+            .rposition(|(x, _)| !(is_transparent(x) && matches!(x, Event::End(_))))
+        else {
+            continue;
+        };
+        let Event::Text(s) = &events[last].0 else {
+            continue;
+        };
+        if let Some(trimmed) = trim_end_punctuations(s, config) {
+            let range = &events[last].1;
+            let removed = range
+                .end
+                .saturating_sub(s.len() - trimmed.len())
+                .max(range.start)..range.end;
+            if is_ignored(&removed, ignore_ranges) {
+                continue;
+            }
+            // a heading of punctuations only is kept
+            if !trimmed.is_empty() || !matches!(events[last - 1].0, Event::Start(_)) {
+                events[last].0 = Event::Text(trimmed.into());
+            }
+        }
+    }
+}
+
+fn trim_end_punctuations(s: &str, config: &Config) -> Option<String> {
+    if s.ends_with("..") || config.rules.skip_abbrs.iter().any(|x| s.ends_with(x)) {
+        return None;
+    }
+    let trimmed = s.trim_end_matches(END_PUNCTUATIONS);
+    (trimmed.len() < s.len()).then(|| trimmed.trim_end().to_string())
+}
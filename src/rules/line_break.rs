@@ -0,0 +1,90 @@
+//! This rule is joining the lines of a paragraph. CommonMark renders a soft
+//! line break as a space, which is a stray gap between Chinese characters.
+//!
+//! Options
+//! - no_line_break_between_full_width: bool
+//!   - `true`: join the lines between full-width letters or punctuations
+//!   - `false`: do nothing, just keep the original format (default)
+//! - replace_line_break_with_space: bool
+//!   - `true`: replace the other soft line breaks with one space, or join the
+//!     lines next to a full-width punctuation
+//!   - `false`: do nothing, just keep the original format (default)
+//!
+//! Details:
+//! - full x full: joined
+//! - half x full, full x half, half x half: kept or one space
+//! - soft line breaks next to inline code or other markups are not joined
+//! - soft line breaks around the markers of containers, e.g. `::: tip`, are
+//!   kept
+//! - soft line breaks in the ignored ranges are kept
+
+use std::ops::Range;
+
+use pulldown_cmark::Event;
+
+use crate::{
+    char_kind::{CharKind, CharKindTrait},
+    config::Config,
+    ignore::is_ignored,
+    template::keeps_line_break,
+};
+
+pub fn rule(
+    events: &mut Vec<(Event<'_>, Range<usize>)>,
+    config: &Config,
+    ignore_ranges: &[Range<usize>],
+) {
+    let mut i = 1;
+    while i + 1 < events.len() {
+        if events[i].0 != Event::SoftBreak
+            || keeps_line_break(events, i, |x| &x.0)
+            || is_ignored(&events[i].1, ignore_ranges)
+        {
+            i += 1;
+            continue;
+        }
+
+        let prev = match &events[i - 1].0 {
+            Event::Text(s) => s.chars().last(),
+            _ => None,
+        };
+        let next = match &events[i + 1].0 {
+            Event::Text(s) => s.chars().next(),
+            _ => None,
+        };
+
+        let is_full_width = |c: Option<char>| c.is_some_and(|c| c.is_full_width());
+        let is_full_width_punctuation =
+            |c: Option<char>| c.is_some_and(|c| c.kind() == CharKind::PunctuationFull);
+
+        if config.rules.no_line_break_between_full_width
+            && is_full_width(prev)
+            && is_full_width(next)
+        {
+            join(events, i, "");
+        } else if config.rules.replace_line_break_with_space {
+            // no space around full-width punctuations, as other rules do
+            let sep = if is_full_width_punctuation(prev) || is_full_width_punctuation(next) {
+                ""
+            } else {
+                " "
+            };
+            join(events, i, sep);
+        } else {
+            i += 1;
+        }
+    }
+}
+
+/// Replace the soft line break at `i` with `sep`, the texts around are merged
+/// into one.
+fn join(events: &mut Vec<(Event<'_>, Range<usize>)>, i: usize, sep: &str) {
+    match (&events[i - 1].0, &events[i + 1].0) {
+        (Event::Text(prev), Event::Text(next)) => {
+            let range = events[i - 1].1.start..events[i + 1].1.end;
+            events[i - 1] = (Event::Text(format!("{prev}{sep}{next}").into()), range);
+            events.drain(i..=i + 1);
+        }
+        _ => events[i].0 = Event::Text(sep.to_string().into()),
+    }
+}
@@ -0,0 +1,218 @@
+//! This rule is checking the punctuations at the end of list items, the items
+//! of a list should all end with `；`, all end with `。`, or none of them.
+//!
+//! Options
+//! - consistent_list_item_ending: Option<ListItemEnding>
+//!   - `majority`: the ending of the most items in the list
+//!   - `semicolon`, `period` or `none`: the given ending
+//!   - `null`: do nothing (default)
+//! - fix_list_item_ending: bool
+//!   - `true`: fix the endings of the items
+//!   - `false`: report the items (default)
+//!
+//! Details:
+//! - each list is checked on its own, nested lists are other lists
+//! - the items without Chinese, or ending with other punctuations like `：`
+//!   and `？`, or with inline code are not checked
+//! - a tie of the majority is broken by the first item
+//! - the endings in the ignored ranges are not fixed
+
+use std::ops::Range;
+
+use pulldown_cmark::{Event, Tag};
+
+use crate::{
+    char_kind::CharKindTrait,
+    config::{Config, ListItemEnding},
+    diagnostic::Diagnostic,
+    ignore::is_ignored,
+    parser::is_transparent,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ending {
+    Semicolon,
+    Period,
+    Nothing,
+}
+
+impl Ending {
+    fn punctuation(&self) -> Option<char> {
+        match self {
+            Ending::Semicolon => Some('；'),
+            Ending::Period => Some('。'),
+            Ending::Nothing => None,
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self.punctuation() {
+            Some(c) => format!("`{c}`"),
+            None => "no punctuation".to_string(),
+        }
+    }
+}
+
+/// The ending of an item by its last text, `None` if it is not checked.
+fn get_ending(s: &str) -> Option<Ending> {
+    let s = s.trim_end();
+    if !s.chars().any(|c| c.is_full_width()) {
+        return None;
+    }
+    match s.chars().last()? {
+        '；' => Some(Ending::Semicolon),
+        '。' => Some(Ending::Period),
+        c if c.is_punctuation() => None,
+        _ => Some(Ending::Nothing),
+    }
+}
+
+/// The items of each list with two items or more, as the index of the start
+/// of the item and the index of its last text. The texts of nested lists are
+/// not in the items.
+fn get_lists<E>(events: &[E], event: impl Fn(&E) -> &Event) -> Vec<Vec<(usize, Option<usize>)>> {
+    let mut res = Vec::new();
+    let mut lists: Vec<Vec<(usize, Option<usize>)>> = Vec::new();
+    let mut code_block = false;
+    for (i, x) in events.iter().enumerate() {
+        let last_text = match event(x) {
+            Event::Start(Tag::List(_)) => {
+                lists.push(Vec::new());
+                continue;
+            }
+            Event::End(Tag::List(_)) => {
+                if let Some(items) = lists.pop().filter(|x| x.len() > 1) {
+                    res.push(items);
+                }
+                continue;
+            }
+            Event::Start(Tag::Item) => {
+                if let Some(items) = lists.last_mut() {
+                    items.push((i, None));
+                }
+                continue;
+            }
+            Event::Start(Tag::CodeBlock(_)) => {
+                code_block = true;
+                continue;
+            }
+            Event::End(Tag::CodeBlock(_)) => {
+                code_block = false;
+                None
+            }
+            Event::Text(_) if !code_block => Some(i),
+            Event::Text(_) | Event::SoftBreak | Event::HardBreak | Event::TaskListMarker(_) => {
+                continue
+            }
+            Event::Start(_) | Event::End(_) => continue,
+            x if is_transparent(x) => continue,
+            // inline code, math and other raw content
+            _ => None,
+        };
+        if let Some(item) = lists.last_mut().and_then(|x| x.last_mut()) {
+            item.1 = last_text;
+        }
+    }
+    res
+}
+
+/// An item ending differently, as the index of the start of the item, the
+/// index of its last text and the ending.
+type Mismatch = (usize, usize, Ending);
+
+/// The expected ending of each list, and the items ending differently.
+fn get_mismatches<E>(
+    events: &[E],
+    event: impl Fn(&E) -> &Event,
+    config: &ListItemEnding,
+) -> Vec<(Ending, Vec<Mismatch>)> {
+    let mut res = Vec::new();
+    for items in get_lists(events, &event) {
+        let checked: Vec<Mismatch> = items
+            .into_iter()
+            .filter_map(|(item, text)| {
+                let text = text?;
+                match event(&events[text]) {
+                    Event::Text(s) => Some((item, text, get_ending(s)?)),
+                    _ => None,
+                }
+            })
+            .collect();
+        let count = |x: Ending| checked.iter().filter(|y| y.2 == x).count();
+        let expected = match config {
+            // the first item wins the tie, as `max_by_key` returns the last one
+            ListItemEnding::Majority => {
+                match checked.iter().rev().map(|x| x.2).max_by_key(|x| count(*x)) {
+                    Some(x) => x,
+                    None => continue,
+                }
+            }
+            ListItemEnding::Semicolon => Ending::Semicolon,
+            ListItemEnding::Period => Ending::Period,
+            ListItemEnding::Nothing => Ending::Nothing,
+        };
+        let mismatches = checked.into_iter().filter(|x| x.2 != expected).collect();
+        res.push((expected, mismatches));
+    }
+    res
+}
+
+// the signature of event rules
+#[allow(clippy::ptr_arg)]
+pub fn rule(
+    events: &mut Vec<(Event<'_>, Range<usize>)>,
+    config: &Config,
+    ignore_ranges: &[Range<usize>],
+) {
+    let Some(ending) = &config.rules.consistent_list_item_ending else {
+        return;
+    };
+    if !config.rules.fix_list_item_ending {
+        return;
+    }
+
+    for (expected, mismatches) in get_mismatches(events, |x| &x.0, ending) {
+        for (_, i, actual) in mismatches {
+            let (Event::Text(s), range) = &events[i] else {
+                continue;
+            };
+            // the last char of the text
+            let end = range.end.saturating_sub(s.len() - s.trim_end().len());
+            let last = s.trim_end().chars().last().map_or(0, char::len_utf8);
+            if is_ignored(&(end.saturating_sub(last)..end), ignore_ranges) {
+                continue;
+            }
+            let mut s = s.trim_end().to_string();
+            if actual.punctuation().is_some() {
+                s.pop();
+            }
+            s.extend(expected.punctuation());
+            events[i].0 = Event::Text(s.into());
+        }
+    }
+}
+
+/// Report the items ending differently, if they are not fixed.
+pub fn check(events: &[(Event<'_>, Range<usize>)], config: &Config) -> Vec<Diagnostic> {
+    let Some(ending) = &config.rules.consistent_list_item_ending else {
+        return Vec::new();
+    };
+    if config.rules.fix_list_item_ending {
+        return Vec::new();
+    }
+
+    let mut res = Vec::new();
+    for (expected, mismatches) in get_mismatches(events, |x| &x.0, ending) {
+        res.extend(mismatches.into_iter().map(|(item, _, actual)| {
+            Diagnostic::new(
+                events[item].1.start,
+                format!(
+                    "the list item ends with {}, but {} is expected",
+                    actual.describe(),
+                    expected.describe()
+                ),
+            )
+        }));
+    }
+    res
+}
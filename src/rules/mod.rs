@@ -1,14 +1,24 @@
-use crate::{config::Config, parser::TextCursor, Context};
+use std::ops::Range;
 
+use pulldown_cmark::Event;
+
+use crate::{config::Config, diagnostic::Diagnostic, parser::TextCursor, Context};
+
+mod heading_numbering;
+mod heading_punctuation;
+mod line_break;
+mod list_item_ending;
 mod punctuation_unification;
 mod punctuation_width;
 mod space_bracket;
 mod space_code;
 mod space_letters;
+mod space_math;
 mod space_punctuation;
 mod space_quote;
 mod space_successive;
 mod space_trim;
+mod trim_space;
 
 pub fn rules() -> Vec<fn(&Context, &mut TextCursor, &Config)> {
     vec![
@@ -19,9 +29,33 @@ pub fn rules() -> Vec<fn(&Context, &mut TextCursor, &Config)> {
         punctuation_unification::rule,
         //
         space_code::rule,
+        space_math::rule,
         space_letters::rule,
         space_punctuation::rule,
         space_quote::rule,
         space_bracket::rule,
     ]
 }
+
+/// A rule fixing the events of the document with their ranges in the source,
+/// the events overlapping the ignored ranges are kept.
+pub type EventRule = fn(&mut Vec<(Event<'_>, Range<usize>)>, &Config, &[Range<usize>]);
+
+/// Rules applied to the events of the whole document, after the text rules.
+pub fn event_rules() -> Vec<EventRule> {
+    vec![
+        trim_space::rule,
+        heading_punctuation::rule,
+        list_item_ending::rule,
+        line_break::rule,
+    ]
+}
+
+/// A rule checking the events of the source with their ranges.
+pub type CheckRule = fn(&[(Event<'_>, Range<usize>)], &Config) -> Vec<Diagnostic>;
+
+/// Rules checking the events of the source, which report the problems
+/// instead of fixing them.
+pub fn check_rules() -> Vec<CheckRule> {
+    vec![heading_numbering::rule, list_item_ending::check]
+}
@@ -1,6 +1,7 @@
 //! This rule will decide whether to keep a space outside inline code with
 //! content like:
 //! - xxx `foo` xxx
+//!
 //! in markdown.
 //!
 //! Options:
@@ -13,12 +14,14 @@
 //! - code x code
 //! - content x code
 //! - code x content
-
-use pulldown_cmark::Event;
+//!
+//! Inline HTML elements in `code_like_html_tags`, e.g. `<kbd>Ctrl</kbd>`, are
+//! treated as inline code.
 
 use crate::{
     char_kind::CharKindTrait,
     config::Config,
+    html::is_code_like,
     parser::{TextCursor, Token},
     Context,
 };
@@ -27,12 +30,12 @@ pub fn rule(_ctx: &Context, cursor: &mut TextCursor, config: &Config) {
     match config.rules.space_outside_code {
         Some(true) => {
             if cursor.current().is_letters()
-                && matches!(cursor.next(), Token::Event(Event::Code(_)))
+                && matches!(cursor.next(), Token::Event(x) if is_code_like(x, config))
             {
                 cursor.add_next(' ');
             }
             if cursor.current().is_letters()
-                && matches!(cursor.prev(), Token::Event(Event::Code(_)))
+                && matches!(cursor.prev(), Token::Event(x) if is_code_like(x, config))
             {
                 cursor.add_prev(' ');
             }
@@ -40,11 +43,11 @@ pub fn rule(_ctx: &Context, cursor: &mut TextCursor, config: &Config) {
         Some(false) => {
             if cursor.prev().is_letters()
                 && cursor.current().is_whitespace()
-                && matches!(cursor.next(), Token::Event(Event::Code(_)))
+                && matches!(cursor.next(), Token::Event(x) if is_code_like(x, config))
             {
                 cursor.delete();
             }
-            if matches!(cursor.prev(), Token::Event(Event::Code(_)))
+            if matches!(cursor.prev(), Token::Event(x) if is_code_like(x, config))
                 && cursor.current().is_whitespace()
                 && cursor.next().is_letters()
             {
@@ -10,6 +10,8 @@
 //!   - `false`: no space between width-mixed content
 //!   - `undefined`: do nothing, just keep the original format
 //!
+//! Inline markups are seen through, and the space is added outside of them.
+//!
 //! Examples (space_between_mixed_width_letters = true):
 //! - *a*啊 -> *a* 啊
 //! - *a *啊 -> *a* 啊
@@ -25,7 +27,7 @@
 use crate::{
     char_kind::{CharKind, CharKindTrait},
     config::Config,
-    parser::TextCursor,
+    parser::{TextCursor, Token},
     Context,
 };
 
@@ -42,16 +44,27 @@ pub fn rule(_ctx: &Context, cursor: &mut TextCursor, config: &Config) {
     // 2. space between mixed width letters
     match config.rules.space_between_mixed_width_letters {
         Some(true) => {
-            if cursor.current().kind() == CharKind::LettersFull
+            let is_unit =
+                |x: Token| matches!(x, Token::Char(c) if config.rules.skip_zh_units.contains(&c));
+
+            if (cursor.current().kind() == CharKind::LettersFull
                 && cursor.next().kind() == CharKind::LettersHalf
-                && !config.rules.skip_zh_units.contains(&cursor.current())
+                && !is_unit(Token::Char(cursor.current())))
+                // the full-width letter may be after an inline markup
+                || (cursor.current().kind() == CharKind::LettersHalf
+                    && cursor.next().kind() == CharKind::LettersFull
+                    && !is_unit(cursor.next())
+                    && matches!(cursor.next_event(), Token::Event(_)))
             {
                 cursor.add_next(' ');
             }
 
-            if cursor.prev().kind() == CharKind::LettersHalf
+            if (cursor.prev().kind() == CharKind::LettersHalf
                 && cursor.current().kind() == CharKind::LettersFull
-                && !config.rules.skip_zh_units.contains(&cursor.current())
+                && !is_unit(Token::Char(cursor.current())))
+                || (cursor.prev().kind() == CharKind::LettersFull
+                    && cursor.current().kind() == CharKind::LettersHalf
+                    && matches!(cursor.prev_event(), Token::Event(_)))
             {
                 cursor.add_prev(' ');
             }
@@ -0,0 +1,48 @@
+//! This rule will decide whether to keep a space outside inline math with
+//! content like:
+//! - xxx $O(n)$ xxx
+//!
+//! in markdown.
+//!
+//! Options:
+//! - space_outside_math: Option<bool>
+//!   - `true`: keep one space outside
+//!   - `false`: no space outside
+//!   - `undefined`: do nothing, just keep the original format (default)
+//!
+//! Details:
+//! - math x math
+//! - content x math
+//! - math x content
+//!
+//! The math itself is kept as it is, see [`crate::math`].
+
+use crate::{char_kind::CharKindTrait, config::Config, math::is_math, parser::TextCursor, Context};
+
+pub fn rule(ctx: &Context, cursor: &mut TextCursor, config: &Config) {
+    match config.rules.space_outside_math {
+        Some(true) => {
+            if cursor.current().is_letters() && is_math(cursor.next_range(), ctx) {
+                cursor.add_next(' ');
+            }
+            if cursor.current().is_letters() && is_math(cursor.prev_range(), ctx) {
+                cursor.add_prev(' ');
+            }
+        }
+        Some(false) => {
+            if cursor.prev().is_letters()
+                && cursor.current().is_whitespace()
+                && is_math(cursor.next_range(), ctx)
+            {
+                cursor.delete();
+            }
+            if is_math(cursor.prev_range(), ctx)
+                && cursor.current().is_whitespace()
+                && cursor.next().is_letters()
+            {
+                cursor.delete();
+            }
+        }
+        None => (),
+    }
+}
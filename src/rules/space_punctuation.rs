@@ -45,7 +45,7 @@ pub fn rule(_ctx: &Context, cursor: &mut TextCursor, config: &Config) {
 
     // 2. space after half width punctuation
     match config.rules.space_after_half_width_punctuation {
-        Some(true) => {
+        Some(true)
             if cursor.current().kind() == CharKind::PunctuationHalf
                 && cursor.current().is_common_punctuation()
                 && cursor.next().kind() != CharKind::Space
@@ -54,20 +54,18 @@ pub fn rule(_ctx: &Context, cursor: &mut TextCursor, config: &Config) {
                     && cursor.next().is_common_punctuation())
                 // skip half-width punctuations between half-width content without space
                 && !(cursor.next().kind() == CharKind::LettersHalf
-                    && cursor.prev().kind() == CharKind::LettersHalf)
-            {
-                cursor.add_next(' ');
-            }
+                    && cursor.prev().kind() == CharKind::LettersHalf) =>
+        {
+            cursor.add_next(' ');
         }
-        Some(false) => {
+        Some(false)
             if cursor.prev().kind() == CharKind::PunctuationHalf
                 && cursor.prev().is_common_punctuation()
-                && cursor.current().is_whitespace()
-            {
-                cursor.delete();
-            }
+                && cursor.current().is_whitespace() =>
+        {
+            cursor.delete();
         }
-        None => (),
+        _ => (),
     }
 
     // 3. no space after full width punctuation
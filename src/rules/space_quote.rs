@@ -40,12 +40,12 @@ pub fn rule(ctx: &Context, cursor: &mut TextCursor, config: &Config) {
         Some(true) => {
             if cursor.prev().is_letters()
                 && cursor.current().is_half_width_quote()
-                && ctx.half_width_double_quote_count % 2 == 0
+                && ctx.half_width_double_quote_count.is_multiple_of(2)
             {
                 cursor.add_prev(' ');
             }
             if cursor.current().is_half_width_quote()
-                && ctx.half_width_double_quote_count % 2 == 0
+                && ctx.half_width_double_quote_count.is_multiple_of(2)
                 && cursor.next().is_letters()
             {
                 cursor.add_next(' ');
@@ -55,12 +55,12 @@ pub fn rule(ctx: &Context, cursor: &mut TextCursor, config: &Config) {
             if cursor.prev().is_letters()
                 && cursor.current().is_whitespace()
                 && cursor.next().is_half_width_quote()
-                && ctx.half_width_double_quote_count % 2 == 0
+                && ctx.half_width_double_quote_count.is_multiple_of(2)
             {
                 cursor.delete();
             }
             if cursor.prev().is_half_width_quote()
-                && ctx.half_width_double_quote_count % 2 == 0
+                && ctx.half_width_double_quote_count.is_multiple_of(2)
                 && cursor.current().is_whitespace()
                 && cursor.next().is_letters()
             {
@@ -11,8 +11,8 @@ use crate::{
 pub fn rule(_ctx: &Context, cursor: &mut TextCursor, config: &Config) {
     if config.rules.no_space_inside_wrapper
         && cursor.current().is_whitespace()
-        && (matches!(cursor.prev(), Token::Event(Event::Start(_)))
-            || matches!(cursor.next(), Token::Event(Event::End(_))))
+        && (matches!(cursor.prev_event(), Token::Event(Event::Start(_)))
+            || matches!(cursor.next_event(), Token::Event(Event::End(_))))
     {
         cursor.delete();
     }
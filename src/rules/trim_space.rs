@@ -0,0 +1,105 @@
+//! This rule is trimming spaces at the start and end of blocks, which are
+//! paragraphs, headings, list items and table cells. The spaces include the
+//! ideographic space `U+3000` and the no-break space `U+00A0`, which are not
+//! trimmed by the parser.
+//!
+//! Options
+//! - trim_space: bool
+//!   - `true`: remove the spaces at the start and end of blocks
+//!   - `false`: do nothing, just keep the original format (default)
+//!
+//! Details:
+//! - the blocks are found by their `Start`/`End` events, and inline markups
+//!   at the edges are seen through, e.g. `　**文字**　` -> `**文字**`
+//! - the spaces in the ignored ranges are kept
+
+use std::ops::Range;
+
+use pulldown_cmark::{Event, Tag};
+
+use crate::{config::Config, ignore::is_ignored, parser::is_transparent};
+
+fn is_block(tag: &Tag) -> bool {
+    matches!(
+        tag,
+        Tag::Paragraph | Tag::Heading(..) | Tag::Item | Tag::TableCell
+    )
+}
+
+// the signature of event rules
+#[allow(clippy::ptr_arg)]
+pub fn rule(
+    events: &mut Vec<(Event<'_>, Range<usize>)>,
+    config: &Config,
+    ignore_ranges: &[Range<usize>],
+) {
+    if !config.rules.trim_space {
+        return;
+    }
+
+    for i in 0..events.len() {
+        match &events[i].0 {
+            Event::Start(tag) if is_block(tag) => trim_start(events, i + 1, ignore_ranges),
+            Event::End(tag) if is_block(tag) && i > 0 => trim_end(events, i - 1, ignore_ranges),
+            _ => (),
+        }
+    }
+}
+
+/// Trim the texts from `i` forward, until a char which is not a space.
+fn trim_start(
+    events: &mut [(Event<'_>, Range<usize>)],
+    mut i: usize,
+    ignore_ranges: &[Range<usize>],
+) {
+    while let Some((event, range)) = events.get(i) {
+        match event {
+            Event::Text(s) => {
+                let trimmed = s.trim_start();
+                let spaces = range.start..range.start + s.len() - trimmed.len();
+                if is_ignored(&spaces, ignore_ranges) {
+                    return;
+                }
+                let done = !trimmed.is_empty();
+                events[i].0 = Event::Text(trimmed.to_string().into());
+                if done {
+                    return;
+                }
+            }
+            Event::TaskListMarker(_) => (),
+            event if is_transparent(event) && matches!(event, Event::Start(_)) => (),
+            _ => return,
+        }
+        i += 1;
+    }
+}
+
+/// Trim the texts from `i` backward, until a char which is not a space.
+fn trim_end(
+    events: &mut [(Event<'_>, Range<usize>)],
+    mut i: usize,
+    ignore_ranges: &[Range<usize>],
+) {
+    loop {
+        match &events[i] {
+            (Event::Text(s), range) => {
+                let trimmed = s.trim_end();
+                let spaces = range.end.saturating_sub(s.len() - trimmed.len())..range.end;
+                if is_ignored(&spaces, ignore_ranges) {
+                    return;
+                }
+                let done = !trimmed.is_empty();
+                events[i].0 = Event::Text(trimmed.to_string().into());
+                if done {
+                    return;
+                }
+            }
+            (event, _) if is_transparent(event) && matches!(event, Event::End(_)) => (),
+            _ => return,
+        }
+        if i == 0 {
+            return;
+        }
+        i -= 1;
+    }
+}
@@ -0,0 +1,146 @@
+//! Elements in `skip_elements`, whose text is kept as it is, e.g. quoted legal
+//! text in blockquotes or generated API tables.
+//!
+//! - tables, blockquotes, lists and footnotes are replaced with one raw event
+//!   of the source, so they are written back byte by byte
+//! - the texts of headings and links are replaced with raw events of the
+//!   source, while the markups are still written as other elements
+
+use std::ops::Range;
+
+use pulldown_cmark::{Event, Tag};
+
+use crate::config::{Config, Element};
+
+impl Element {
+    fn matches(&self, tag: &Tag) -> bool {
+        matches!(
+            (self, tag),
+            (Element::Heading, Tag::Heading(..))
+                | (Element::Blockquote, Tag::BlockQuote)
+                | (Element::List, Tag::List(_))
+                | (Element::Table, Tag::Table(_))
+                | (Element::Footnote, Tag::FootnoteDefinition(_))
+                | (Element::LinkText, Tag::Link(..))
+        )
+    }
+
+    /// Whether the whole element is written as its source.
+    fn is_block(&self) -> bool {
+        !matches!(self, Element::Heading | Element::LinkText)
+    }
+}
+
+/// Replace the block elements of `skip_elements` with raw events of the
+/// source.
+fn protect_blocks<'a>(
+    events: Vec<(Event<'a>, Range<usize>)>,
+    text: &str,
+    config: &Config,
+) -> Vec<(Event<'a>, Range<usize>)> {
+    let mut res = Vec::with_capacity(events.len());
+    let mut events = events.into_iter();
+    while let Some((event, range)) = events.next() {
+        let skipped = match &event {
+            Event::Start(tag) => config
+                .skip_elements
+                .iter()
+                .any(|x| x.is_block() && x.matches(tag)),
+            _ => false,
+        };
+        if !skipped {
+            res.push((event, range));
+            continue;
+        }
+
+        let mut nested = 1;
+        for (event, _) in events.by_ref() {
+            match event {
+                Event::Start(_) => nested += 1,
+                Event::End(_) => nested -= 1,
+                _ => (),
+            }
+            if nested == 0 {
+                break;
+            }
+        }
+        res.extend(raw_block(text, range));
+    }
+    res
+}
+
+/// The raw event of a block, written as a paragraph, as the writer does not
+/// separate a raw event from the blocks around it. Inside a container like a
+/// list item, the prefixes of the container like `> ` are removed from the
+/// source, as the writer adds them.
+pub(crate) fn raw_block<'a>(text: &str, range: Range<usize>) -> [(Event<'a>, Range<usize>); 3] {
+    let line_start = text[..range.start].rfind('\n').map_or(0, |x| x + 1);
+    let prefix = range.start - line_start;
+    let source = text[range.clone()]
+        .trim_end_matches('\n')
+        .lines()
+        .enumerate()
+        .map(|(i, line)| match i {
+            0 => line,
+            _ => {
+                let len = line
+                    .bytes()
+                    .take(prefix)
+                    .take_while(|c| matches!(c, b' ' | b'\t' | b'>'))
+                    .count();
+                &line[len..]
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    [
+        (Event::Start(Tag::Paragraph), range.clone()),
+        (Event::Html(source.into()), range.clone()),
+        (Event::End(Tag::Paragraph), range),
+    ]
+}
+
+/// Replace the elements of `skip_elements`, or the texts inside them with raw
+/// events, except the code blocks, which are never formatted.
+pub(crate) fn protect_elements<'a>(
+    events: Vec<(Event<'a>, Range<usize>)>,
+    text: &str,
+    config: &Config,
+) -> Vec<(Event<'a>, Range<usize>)> {
+    if config.skip_elements.is_empty() {
+        return events;
+    }
+    let events = protect_blocks(events, text, config);
+
+    // whether each tag of the stack is skipped
+    let mut stack = Vec::new();
+    let mut code_block = false;
+    // the end of the last replaced text, the backslashes of escapes are not in
+    // the ranges of the texts, e.g. `\*`
+    let mut end = 0;
+    events
+        .into_iter()
+        .map(|(event, range)| {
+            match &event {
+                Event::Start(tag) => {
+                    stack.push(config.skip_elements.iter().any(|x| x.matches(tag)));
+                    code_block |= matches!(tag, Tag::CodeBlock(_));
+                }
+                Event::End(tag) => {
+                    stack.pop();
+                    code_block &= !matches!(tag, Tag::CodeBlock(_));
+                }
+                Event::Text(_) if !code_block && stack.contains(&true) => {
+                    let mut range = range;
+                    if range.start > end && text[..range.start].ends_with('\\') {
+                        range.start -= 1;
+                    }
+                    end = range.end;
+                    return (Event::Html(text[range.clone()].to_string().into()), range);
+                }
+                _ => (),
+            }
+            (event, range)
+        })
+        .collect()
+}
@@ -0,0 +1,197 @@
+//! Tables are written by ourselves instead of `pulldown_cmark_to_cmark`, so
+//! that pipes inside cells are escaped and the delimiter row is always legal.
+//! Columns are padded by display width, where a CJK character takes two
+//! columns, so that tables stay aligned in monospace editors.
+//!
+//! The rows with more or fewer cells than the delimiter row are cut or filled
+//! by the parser, so such tables are written as they are instead.
+
+use std::{fmt, ops::Range};
+
+use pulldown_cmark::{Alignment, Event, Tag};
+use pulldown_cmark_to_cmark::{cmark, State};
+use unicode_width::UnicodeWidthStr;
+
+use crate::skip::raw_block;
+
+const NEWLINES_AFTER_TABLE: usize = 2;
+/// The delimiter row needs at least three dashes or colons in each column.
+const MIN_COLUMN_WIDTH: usize = 3;
+
+struct Table {
+    alignments: Vec<Alignment>,
+    rows: Vec<Vec<String>>,
+    /// Display width of each column.
+    widths: Vec<usize>,
+}
+
+impl Table {
+    fn from_events(events: &[Event]) -> Result<Self, fmt::Error> {
+        let mut alignments = Vec::new();
+        let mut rows = Vec::new();
+        let mut cell = Vec::new();
+        let mut in_cell = false;
+
+        for event in events {
+            match event {
+                Event::Start(Tag::Table(x)) => alignments = x.clone(),
+                Event::Start(Tag::TableHead | Tag::TableRow) => rows.push(Vec::new()),
+                Event::Start(Tag::TableCell) => in_cell = true,
+                Event::End(Tag::TableCell) => {
+                    let mut s = String::new();
+                    cmark(cell.drain(..), &mut s)?;
+                    rows.last_mut().unwrap().push(s);
+                    in_cell = false;
+                }
+                event if in_cell => cell.push(escape_pipes(event.clone())),
+                _ => (),
+            }
+        }
+
+        let widths = (0..alignments.len())
+            .map(|i| {
+                rows.iter()
+                    .filter_map(|row| row.get(i))
+                    .map(|cell| cell.width())
+                    .fold(MIN_COLUMN_WIDTH, usize::max)
+            })
+            .collect();
+
+        Ok(Self {
+            alignments,
+            rows,
+            widths,
+        })
+    }
+
+    fn write_row<W: fmt::Write>(&self, row: &[String], writer: &mut W) -> fmt::Result {
+        for (i, (alignment, width)) in self.alignments.iter().zip(&self.widths).enumerate() {
+            let cell = row.get(i).map(String::as_str).unwrap_or_default();
+            let padding = width - cell.width();
+            let (left, right) = match alignment {
+                Alignment::None | Alignment::Left => (0, padding),
+                Alignment::Center => (padding / 2, padding - padding / 2),
+                Alignment::Right => (padding, 0),
+            };
+            write!(writer, "| {:left$}{cell}{:right$} ", "", "")?;
+        }
+        writer.write_char('|')
+    }
+
+    fn write_delimiter_row<W: fmt::Write>(&self, writer: &mut W) -> fmt::Result {
+        for (alignment, width) in self.alignments.iter().zip(&self.widths) {
+            let (left, right) = match alignment {
+                Alignment::None => ("", ""),
+                Alignment::Left => (":", ""),
+                Alignment::Center => (":", ":"),
+                Alignment::Right => ("", ":"),
+            };
+            let dashes = "-".repeat(width - left.len() - right.len());
+            write!(writer, "| {left}{dashes}{right} ")?;
+        }
+        writer.write_char('|')
+    }
+}
+
+/// Pipes in cells are escaped, or they would split the cell. The leading
+/// pipe of a text is escaped by `pulldown_cmark_to_cmark` already, and the
+/// escaped pipes are kept as they are in inline code.
+fn escape_pipes(event: Event) -> Event {
+    match event {
+        Event::Text(s) => match s.strip_prefix('|') {
+            Some(rest) => Event::Text(format!("|{}", rest.replace('|', "\\|")).into()),
+            None => Event::Text(s.replace('|', "\\|").into()),
+        },
+        event => event,
+    }
+}
+
+/// Whether a row of the events from `Start(TableRow)` to `End(TableRow)` has
+/// more or fewer cells in the source than the delimiter row. The cells filled
+/// by the parser are empty at the end of the row, and the cells cut are left
+/// after the last cell.
+fn is_ragged(row: &[(Event, Range<usize>)], text: &str) -> bool {
+    let cells: Vec<_> = row
+        .iter()
+        .filter(|(event, _)| matches!(event, Event::Start(Tag::TableCell)))
+        .map(|(_, range)| range)
+        .collect();
+    let row_end = row[0].1.end;
+    cells.iter().any(|x| x.is_empty() && x.start == row_end)
+        || cells.last().is_some_and(|x| {
+            !text[x.end..row_end]
+                .trim_matches(|c: char| c.is_whitespace() || c == '|')
+                .is_empty()
+        })
+}
+
+/// Replace the tables with ragged rows with raw events of the source, so no
+/// cell is lost.
+pub(crate) fn protect_ragged_tables<'a>(
+    mut events: Vec<(Event<'a>, Range<usize>)>,
+    text: &str,
+) -> Vec<(Event<'a>, Range<usize>)> {
+    let mut i = 0;
+    while i < events.len() {
+        if !matches!(events[i].0, Event::Start(Tag::Table(_))) {
+            i += 1;
+            continue;
+        }
+        let end = i + events[i..]
+            .iter()
+            .position(|(event, _)| matches!(event, Event::End(Tag::Table(_))))
+            .unwrap();
+        let mut rows = events[i..end]
+            .iter()
+            .enumerate()
+            .filter(|(_, (event, _))| matches!(event, Event::Start(Tag::TableRow)))
+            .map(|(j, _)| i + j);
+        if rows.any(|start| {
+            let len = events[start..]
+                .iter()
+                .position(|(event, _)| matches!(event, Event::End(Tag::TableRow)))
+                .unwrap();
+            is_ragged(&events[start..=start + len], text)
+        }) {
+            let range = events[i].1.clone();
+            events.splice(i..=end, raw_block(text, range));
+            i += 3;
+        } else {
+            i = end + 1;
+        }
+    }
+    events
+}
+
+/// Write the events of a table, from `Start(Table)` to `End(Table)`.
+pub(crate) fn write_table<W: fmt::Write>(
+    events: &[Event],
+    state: &mut State<'static>,
+    writer: &mut W,
+) -> fmt::Result {
+    let table = Table::from_events(events)?;
+
+    let padding = state.padding.concat();
+    while state.newlines_before_start != 0 {
+        state.newlines_before_start -= 1;
+        writer.write_char('\n')?;
+        writer.write_str(&padding)?;
+    }
+
+    for (i, row) in table.rows.iter().enumerate() {
+        if i > 0 {
+            writer.write_char('\n')?;
+            writer.write_str(&padding)?;
+        }
+        table.write_row(row, writer)?;
+        if i == 0 {
+            writer.write_char('\n')?;
+            writer.write_str(&padding)?;
+            table.write_delimiter_row(writer)?;
+        }
+    }
+
+    state.newlines_before_start = NEWLINES_AFTER_TABLE;
+    state.last_was_html = false;
+    Ok(())
+}
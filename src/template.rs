@@ -0,0 +1,84 @@
+//! Template tags of static site generators, e.g. `{{< figure >}}` or
+//! `{% include %}`, which are protected like math, see [`protect_ranges`].
+//!
+//! VuePress containers like `::: tip 提示` are protected except the title,
+//! and the line breaks around the markers are always kept, or the container
+//! is broken.
+
+use std::ops::Range;
+
+use pulldown_cmark::Event;
+
+use crate::{
+    config::{Config, Template},
+    ignore::{compile_ignores, get_ignore_ranges},
+    parser::is_inline,
+    protect::protect_ranges,
+};
+
+/// Hugo shortcodes, e.g. `{{< figure src="a.png" >}}` or `{{% note %}}`.
+const HUGO_RE: [&str; 2] = [r"(?s)\{\{<.*?>\}\}", r"(?s)\{\{%.*?%\}\}"];
+/// Jinja tags, expressions and comments, e.g. `{% if x %}`, `{{ x }}` or `{# x #}`.
+const JINJA_RE: [&str; 3] = [r"(?s)\{%.*?%\}", r"(?s)\{\{.*?\}\}", r"(?s)\{#.*?#\}"];
+/// Liquid tags and objects, e.g. `{% include x.html %}` or `{{ page.title }}`.
+const LIQUID_RE: [&str; 2] = [r"(?s)\{%.*?%\}", r"(?s)\{\{.*?\}\}"];
+/// Vue interpolations, e.g. `{{ $t('key') }}`, and the markers of containers,
+/// e.g. `::: tip` of `::: tip 提示`.
+const VUEPRESS_RE: [&str; 2] = [
+    r"(?s)\{\{.*?\}\}",
+    r"(?m)^[ \t>]*(?<ignore>:{3,}[ \t]*[A-Za-z0-9_-]*)",
+];
+
+impl Template {
+    fn regexes(&self) -> &'static [&'static str] {
+        match self {
+            Template::Hugo => &HUGO_RE,
+            Template::Jinja => &JINJA_RE,
+            Template::Liquid => &LIQUID_RE,
+            Template::VuePress => &VUEPRESS_RE,
+        }
+    }
+}
+
+/// Replace the template tags of `templates` with raw events.
+pub(crate) fn protect_templates<'a>(
+    events: Vec<(Event<'a>, Range<usize>)>,
+    text: &str,
+    config: &Config,
+) -> Vec<(Event<'a>, Range<usize>)> {
+    let regexes: Vec<&str> = config
+        .templates
+        .iter()
+        .flat_map(|x| x.regexes().iter().copied())
+        .collect();
+    if regexes.is_empty() {
+        return events;
+    }
+    // the regexes of templates are built in
+    let ranges = get_ignore_ranges(text, &compile_ignores(&regexes).unwrap());
+    protect_ranges(events, text, ranges)
+}
+
+/// Whether the event is the marker of a container, e.g. `::: tip`.
+fn is_container_marker(event: &Event) -> bool {
+    matches!(event, Event::Html(s) if s.starts_with(":::"))
+}
+
+/// Whether the soft break at `i` ends a line starting with a container marker,
+/// or is followed by one, so it can not be joined or re-wrapped.
+pub(crate) fn keeps_line_break<E>(events: &[E], i: usize, event: impl Fn(&E) -> &Event) -> bool {
+    let line_start = events[..i]
+        .iter()
+        .rposition(|x| match event(x) {
+            Event::SoftBreak | Event::HardBreak => true,
+            Event::Start(tag) | Event::End(tag) => !is_inline(tag),
+            _ => false,
+        })
+        .map_or(0, |x| x + 1);
+    events
+        .get(line_start)
+        .is_some_and(|x| is_container_marker(event(x)))
+        || events
+            .get(i + 1)
+            .is_some_and(|x| is_container_marker(event(x)))
+}
@@ -0,0 +1,300 @@
+//! Re-wrap paragraphs to a width counted in display columns, where a CJK
+//! character takes two columns.
+//!
+//! A paragraph is split into segments which can not be broken, lines are then
+//! filled with segments greedily.
+//! - Latin words, inline code, links, images and inline HTML are never broken
+//! - a line can be broken at a space, or between two chars if any of them is
+//!   a full-width char
+//! - kinsoku: a line never starts with a punctuation like `，` or `。`, and
+//!   never ends with a punctuation like `“` or `（`
+//! - a line never starts with something which would be parsed as a block,
+//!   like `-` or `1.`
+//! - the lines of container markers, e.g. `::: tip`, are kept
+
+use std::{mem, slice};
+
+use pulldown_cmark::{Event, Tag};
+use pulldown_cmark_to_cmark::cmark;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::{char_kind::CharKindTrait, template::keeps_line_break};
+
+/// Punctuations which can not start a line.
+const NO_LINE_START: &str = "，。、；：？！）》」』】〕〉”’…—～·％";
+/// Punctuations which can not end a line.
+const NO_LINE_END: &str = "（《「『【〔〈“‘";
+
+enum Unit<'a> {
+    Char(char),
+    Space,
+    /// A soft break, which is a space unless it is between full-width chars.
+    SoftBreak,
+    HardBreak(Event<'a>),
+    /// Events which can not be broken, e.g. inline code or a link.
+    Atom {
+        events: Vec<Event<'a>>,
+        width: usize,
+        line_start: bool,
+    },
+    Open(Event<'a>),
+    Close(Event<'a>),
+}
+
+impl Unit<'_> {
+    fn width(&self) -> usize {
+        match self {
+            Unit::Char(c) => c.width().unwrap_or(0),
+            Unit::Space => 1,
+            Unit::Atom { width, .. } => *width,
+            Unit::Open(event) | Unit::Close(event) => rendered_width(slice::from_ref(event)),
+            Unit::SoftBreak | Unit::HardBreak(_) => 0,
+        }
+    }
+}
+
+/// A run of units which can not be broken.
+struct Segment {
+    start: usize,
+    end: usize,
+    width: usize,
+    space_before: bool,
+}
+
+fn rendered_width(events: &[Event]) -> usize {
+    let mut s = String::new();
+    cmark(events.iter(), &mut s).unwrap();
+    s.width()
+}
+
+fn to_units<'a>(events: &[Event<'a>]) -> Vec<Unit<'a>> {
+    let mut units = Vec::new();
+    let mut i = 0;
+    while i < events.len() {
+        match &events[i] {
+            Event::Text(s) => {
+                for c in s.chars() {
+                    match c {
+                        ' ' | '\t' if matches!(units.last(), Some(Unit::Space)) => (),
+                        ' ' | '\t' => units.push(Unit::Space),
+                        c => units.push(Unit::Char(c)),
+                    }
+                }
+            }
+            Event::SoftBreak if keeps_line_break(events, i, |x| x) => {
+                units.push(Unit::HardBreak(Event::SoftBreak))
+            }
+            Event::SoftBreak => units.push(Unit::SoftBreak),
+            Event::HardBreak => units.push(Unit::HardBreak(Event::HardBreak)),
+            event @ Event::Start(Tag::Emphasis | Tag::Strong | Tag::Strikethrough) => {
+                units.push(Unit::Open(event.clone()))
+            }
+            event @ Event::End(Tag::Emphasis | Tag::Strong | Tag::Strikethrough) => {
+                units.push(Unit::Close(event.clone()))
+            }
+            Event::Start(Tag::Link(..) | Tag::Image(..)) => {
+                // the whole link is kept in one line
+                let mut depth = 0;
+                let mut atom = Vec::new();
+                for event in &events[i..] {
+                    match event {
+                        Event::Start(_) => depth += 1,
+                        Event::End(_) => depth -= 1,
+                        _ => (),
+                    }
+                    atom.push(match event {
+                        Event::SoftBreak => Event::Text(" ".into()),
+                        event => event.clone(),
+                    });
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                i += atom.len() - 1;
+                units.push(Unit::Atom {
+                    width: rendered_width(&atom),
+                    events: atom,
+                    line_start: true,
+                });
+            }
+            event => units.push(Unit::Atom {
+                width: rendered_width(slice::from_ref(event)),
+                // an HTML tag at the line start may start an HTML block
+                line_start: !matches!(event, Event::Html(s) if s.starts_with('<')),
+                events: vec![event.clone()],
+            }),
+        }
+        i += 1;
+    }
+
+    // soft breaks between full-width chars are removed, others are spaces
+    let full_width = |c: Option<char>| c.is_some_and(|c| c.is_full_width());
+    let to_space: Vec<bool> = (0..units.len())
+        .map(|i| {
+            matches!(units[i], Unit::SoftBreak)
+                && !(full_width(char_before(&units, i)) && full_width(char_after(&units, i + 1)))
+        })
+        .collect();
+    let mut res = Vec::with_capacity(units.len());
+    for (unit, to_space) in units.into_iter().zip(to_space) {
+        match unit {
+            Unit::SoftBreak if to_space && !matches!(res.last(), Some(Unit::Space)) => {
+                res.push(Unit::Space)
+            }
+            Unit::SoftBreak => (),
+            Unit::Space if matches!(res.last(), Some(Unit::Space)) => (),
+            unit => res.push(unit),
+        }
+    }
+    res
+}
+
+/// The char before the unit at `i`, seeing through closing markers.
+fn char_before(units: &[Unit], i: usize) -> Option<char> {
+    units[..i]
+        .iter()
+        .rev()
+        .find(|x| !matches!(x, Unit::Close(_)))
+        .and_then(|x| match x {
+            Unit::Char(c) => Some(*c),
+            _ => None,
+        })
+}
+
+/// The char at or after the unit at `i`, seeing through opening markers.
+fn char_after(units: &[Unit], i: usize) -> Option<char> {
+    units[i..]
+        .iter()
+        .find(|x| !matches!(x, Unit::Open(_)))
+        .and_then(|x| match x {
+            Unit::Char(c) => Some(*c),
+            _ => None,
+        })
+}
+
+/// Whether a line can be broken between two adjacent units without a space.
+fn breakable_between(units: &[Unit], i: usize) -> bool {
+    if matches!(units[i - 1], Unit::Open(_)) || matches!(units[i], Unit::Close(_)) {
+        return false;
+    }
+    let (before, after) = (char_before(units, i), char_after(units, i));
+    (before.is_some_and(|c| c.is_full_width()) || after.is_some_and(|c| c.is_full_width()))
+        && !before.is_some_and(|c| NO_LINE_END.contains(c))
+}
+
+/// Whether the unit at `i` can start a line.
+fn can_start_line(units: &[Unit], i: usize) -> bool {
+    let first = units[i..].iter().find(|x| !matches!(x, Unit::Open(_)));
+    match first {
+        Some(Unit::Atom { line_start, .. }) => *line_start,
+        Some(Unit::Char(c)) if NO_LINE_START.contains(*c) => false,
+        Some(Unit::Char(c)) if c.is_ascii_punctuation() => "([\"'".contains(*c),
+        Some(Unit::Char(c)) if c.is_ascii_digit() => {
+            // e.g. `1.` or `1)` which starts an ordered list
+            let mut rest = units[i..]
+                .iter()
+                .skip_while(|x| matches!(x, Unit::Char(c) if c.is_ascii_digit()));
+            !matches!(rest.next(), Some(Unit::Char('.' | ')')))
+        }
+        Some(_) => true,
+        None => false,
+    }
+}
+
+fn to_segments(units: &[Unit]) -> Vec<Segment> {
+    let mut segments: Vec<Segment> = Vec::new();
+    let mut space_before = false;
+    for (i, unit) in units.iter().enumerate() {
+        if matches!(unit, Unit::Space) {
+            space_before = true;
+            continue;
+        }
+        let new_segment = match segments.last() {
+            None => true,
+            // kinsoku: the units are kept in the previous line instead
+            Some(last) => {
+                (space_before
+                    || matches!(units[last.end - 1], Unit::HardBreak(_))
+                    || breakable_between(units, i))
+                    && can_start_line(units, i)
+            }
+        };
+        if new_segment {
+            segments.push(Segment {
+                start: i,
+                end: i,
+                width: 0,
+                space_before: space_before && !segments.is_empty(),
+            });
+        } else if space_before {
+            segments.last_mut().unwrap().width += 1;
+        }
+        let last = segments.last_mut().unwrap();
+        last.end = i + 1;
+        last.width += unit.width();
+        space_before = false;
+    }
+    segments
+}
+
+/// Re-wrap the inline events of a paragraph, so that lines are not wider than
+/// `width` if possible, and the first line is not wider than `first_width`.
+pub(crate) fn wrap_inlines<'a>(
+    events: &[Event<'a>],
+    first_width: usize,
+    width: usize,
+) -> Vec<Event<'a>> {
+    let units = to_units(events);
+    let segments = to_segments(&units);
+
+    let mut res = Vec::new();
+    let mut text = String::new();
+    let mut line_width = 0;
+    let mut max_width = first_width;
+    let mut units = units.into_iter().map(Some).collect::<Vec<_>>();
+    for segment in segments {
+        if line_width > 0 {
+            let space = usize::from(segment.space_before);
+            if line_width + space + segment.width > max_width {
+                flush(&mut res, &mut text);
+                res.push(Event::SoftBreak);
+                line_width = 0;
+                max_width = width;
+            } else if segment.space_before {
+                text.push(' ');
+                line_width += 1;
+            }
+        }
+        line_width += segment.width;
+
+        for unit in units[segment.start..segment.end].iter_mut() {
+            match unit.take().unwrap() {
+                Unit::Char(c) => text.push(c),
+                Unit::Space => text.push(' '),
+                Unit::Atom { events, .. } => {
+                    flush(&mut res, &mut text);
+                    res.extend(events);
+                }
+                Unit::HardBreak(event) => {
+                    flush(&mut res, &mut text);
+                    res.push(event);
+                    line_width = 0;
+                    max_width = width;
+                }
+                Unit::Open(event) | Unit::Close(event) => {
+                    flush(&mut res, &mut text);
+                    res.push(event);
+                }
+                Unit::SoftBreak => (),
+            }
+        }
+    }
+    flush(&mut res, &mut text);
+    res
+}
+
+fn flush<'a>(events: &mut Vec<Event<'a>>, text: &mut String) {
+    if !text.is_empty() {
+        events.push(Event::Text(mem::take(text).into()));
+    }
+}
@@ -0,0 +1,112 @@
+//! Move the spaces inside inline wrappers to outside of them, e.g.
+//! `文字[ foo ](url)文字` -> `文字 [foo](url) 文字`.
+//!
+//! The spaces are moved before the rules, so that the spaces outside are
+//! decided by the rules of letters and punctuations, which see through the
+//! wrappers.
+//!
+//! Options:
+//! - no_space_inside_wrapper: bool
+//!   - `true`: move the spaces out of emphasis, strong, strikethrough and links
+//!   - `false`: do nothing
+
+use std::ops::Range;
+
+use pulldown_cmark::Event;
+
+use crate::{config::Config, parser::is_transparent};
+
+/// Whether a space is needed between the event and a wrapper, or the event
+/// has a space at the edge already.
+fn needs_space(event: Option<&(Event, Range<usize>)>, at_end: bool) -> bool {
+    match event {
+        Some((Event::Text(s), _)) if at_end => !s.ends_with(char::is_whitespace),
+        Some((Event::Text(s), _)) => !s.starts_with(char::is_whitespace),
+        Some((
+            Event::Code(_) | Event::Html(_) | Event::FootnoteReference(_) | Event::End(_),
+            _,
+        )) if at_end => true,
+        Some((
+            Event::Code(_) | Event::Html(_) | Event::FootnoteReference(_) | Event::Start(_),
+            _,
+        )) if !at_end => true,
+        _ => false,
+    }
+}
+
+fn space(offset: usize) -> (Event<'static>, Range<usize>) {
+    (Event::Text(" ".into()), offset..offset)
+}
+
+pub(crate) fn move_spaces_out<'a>(
+    mut events: Vec<(Event<'a>, Range<usize>)>,
+    config: &Config,
+) -> Vec<(Event<'a>, Range<usize>)> {
+    if !config.rules.no_space_inside_wrapper {
+        return events;
+    }
+
+    let mut i = 0;
+    while i < events.len() {
+        match &events[i] {
+            // leading spaces inside, e.g. `[ foo](url)`
+            (event @ Event::Start(_), _) if is_transparent(event) => {
+                let Some((Event::Text(s), range)) = events.get(i + 1) else {
+                    i += 1;
+                    continue;
+                };
+                let trimmed = s.trim_start();
+                if trimmed.len() == s.len() || trimmed.is_empty() {
+                    i += 1;
+                    continue;
+                }
+                let start = range.start + s.len() - trimmed.len();
+                events[i + 1] = (Event::Text(trimmed.to_string().into()), start..range.end);
+
+                // the space goes before all the nested wrappers
+                let mut j = i;
+                while j > 0
+                    && is_transparent(&events[j - 1].0)
+                    && matches!(events[j - 1].0, Event::Start(_))
+                {
+                    j -= 1;
+                }
+                if needs_space(j.checked_sub(1).map(|j| &events[j]), true) {
+                    let offset = events[j].1.start;
+                    events.insert(j, space(offset));
+                    i += 1;
+                }
+            }
+            // trailing spaces inside, e.g. `[foo ](url)`
+            (event @ Event::End(_), _) if is_transparent(event) && i > 0 => {
+                let (Event::Text(s), range) = &events[i - 1] else {
+                    i += 1;
+                    continue;
+                };
+                let trimmed = s.trim_end();
+                if trimmed.len() == s.len() || trimmed.is_empty() {
+                    i += 1;
+                    continue;
+                }
+                let end = range.start + trimmed.len();
+                events[i - 1] = (Event::Text(trimmed.to_string().into()), range.start..end);
+
+                // the space goes after all the nested wrappers
+                let mut j = i;
+                while j + 1 < events.len()
+                    && is_transparent(&events[j + 1].0)
+                    && matches!(events[j + 1].0, Event::End(_))
+                {
+                    j += 1;
+                }
+                if needs_space(events.get(j + 1), false) {
+                    let offset = events[j].1.end;
+                    events.insert(j + 1, space(offset));
+                }
+            }
+            _ => (),
+        }
+        i += 1;
+    }
+    events
+}
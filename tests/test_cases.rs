@@ -1,4 +1,8 @@
-use zhlint::{config::Config, run};
+use zhlint::{
+    anchor::{update_fragment_links, AnchorChange},
+    config::{Config, Slugger},
+    run, run_with_report,
+};
 
 fn run_text(text: &str, config: &Config) -> String {
     let mut res = String::new();
@@ -28,6 +32,10 @@ fn test_abbrs() {
         run_text(r#"运行时 + 编译器 vs. 只包含运行时"#, &Config::default()),
         r#"运行时 + 编译器 vs. 只包含运行时"#
     );
+    assert_eq!(
+        run_text(r#"运行时 vs."#, &Config::default()),
+        r#"运行时 vs."#
+    );
 }
 
 #[test]
@@ -131,6 +139,30 @@ fn test_math_exp() {
     assert_eq!(run_text(r"a || b", &Config::default()), r"a || b");
 }
 
+#[test]
+fn test_math_with_default_preset() {
+    let mut config = Config::default();
+    // math is not protected by default, the rules apply inside
+    assert_eq!(run_text("中文$a ,b$中文", &config), "中文$a,b$中文");
+
+    config.extensions.math = true;
+    assert_eq!(run_text("$x$和$y$", &config), "$x$和$y$");
+    assert_eq!(run_text("中文$a ,b$中文", &config), "中文$a ,b$中文");
+    assert_eq!(run_text("中文$f( x )$中文", &config), "中文$f( x )$中文");
+    assert_eq!(
+        run_text(r#"中文$x "y"$中文"#, &config),
+        r#"中文$x "y"$中文"#
+    );
+    assert_eq!(run_text("公式$$a ,b$$如下", &config), "公式$$a ,b$$如下");
+
+    config.rules.space_outside_math = Some(true);
+    assert_eq!(run_text("$x$和$y$", &config), "$x$ 和 $y$");
+    assert_eq!(
+        run_text("复杂度$O(n)$的算法", &config),
+        "复杂度 $O(n)$ 的算法"
+    );
+}
+
 #[test]
 fn test_arrow_chars() {
     assert_eq!(
@@ -138,3 +170,378 @@ fn test_arrow_chars() {
         r"Chrome 顶部导航 > 窗口 > 任务管理"
     );
 }
+
+#[test]
+fn test_front_matter_keys() {
+    let config = Config {
+        front_matter_keys: vec!["title".to_string(), "description".to_string()],
+        ..Default::default()
+    };
+
+    assert_eq!(
+        run_text(
+            "---\ntitle: 使用Vue开发,很简单\ndescription: \"“Vue”是一个框架,\\\"好用\\\"\" # 注释\nexcerpt: 不处理,保持原样\ntags: [中文,English]\nlong: |\n  多行文本,不处理\n---\n正文\n",
+            &config
+        ),
+        "---\ntitle: 使用 Vue 开发，很简单\ndescription: \"“Vue”是一个框架，“好用”\" # 注释\nexcerpt: 不处理,保持原样\ntags: [中文,English]\nlong: |\n  多行文本,不处理\n---\n正文"
+    );
+    assert_eq!(
+        run_text(
+            "---\ntitle: '使用''Vue''开发'\ndescription: >-\n  多行文本,\n  也会处理\n---\n正文\n",
+            &config
+        ),
+        "---\ntitle: '使用‘Vue’开发'\ndescription: >-\n  多行文本，\n  也会处理\n---\n正文"
+    );
+    assert_eq!(
+        run_text(
+            "+++\ntitle = \"使用Vue开发\"\ndescription = '中文English'\n[extra]\ntitle = \"中文English\"\n+++\n正文\n",
+            &config
+        ),
+        "+++\ntitle = \"使用 Vue 开发\"\ndescription = '中文 English'\n[extra]\ntitle = \"中文English\"\n+++\n正文"
+    );
+    assert_eq!(
+        run_text("---\ntitle: 使用Vue开发\n---\n正文\n", &Config::default()),
+        "---\ntitle: 使用Vue开发\n---\n正文"
+    );
+}
+
+#[test]
+fn test_gfm_table() {
+    assert_eq!(
+        run_text(
+            "| 名称 | 说明 |\n| :--- | ---: |\n| Vue框架 | 前端框架,很好用 |\n| `a\\|b` | b\\|c |",
+            &Config::default()
+        ),
+        "| 名称     |             说明 |\n| :------- | ---------------: |\n| Vue 框架 | 前端框架，很好用 |\n| `a\\|b`   |             b\\|c |"
+    );
+    assert_eq!(
+        run_text("|a|中文|\n|:-:|-|\n|English单词|x|", &Config::default()),
+        "|      a       | 中文 |\n| :----------: | ---- |\n| English 单词 | x    |"
+    );
+    // the rows with more or fewer cells are not cut or filled, nor padded
+    for text in [
+        "| a | b |\n|---|---|\n| 多余 | 单元 | 格 |\n\n文字,文字",
+        "| a | b |\n|---|---|\n| 单元格 |\n\n文字,文字",
+        "* 列表\n  \n  | a | b |\n  |---|---|\n  | 多余 | 单元 | 格 |",
+    ] {
+        assert_eq!(
+            run_text(text, &Config::default()),
+            text.replace("文字,文字", "文字，文字")
+        );
+    }
+    // the other tables are still padded
+    assert_eq!(
+        run_text(
+            "| a | b |\n|---|---|\n| 多余 | 单元 | 格 |\n\n| a | 中文 |\n|---|---|\n| 单元格 | x |",
+            &Config::default()
+        ),
+        "| a | b |\n|---|---|\n| 多余 | 单元 | 格 |\n\n| a      | 中文 |\n| ------ | ---- |\n| 单元格 | x    |"
+    );
+}
+
+#[test]
+fn test_gfm_inlines() {
+    assert_eq!(
+        run_text(
+            "- [ ] 任务一,未完成\n- [x] 完成English任务",
+            &Config::default()
+        ),
+        "* [ ] 任务一，未完成\n* [x] 完成 English 任务"
+    );
+    assert_eq!(
+        run_text("还有~~删除线English~~文字", &Config::default()),
+        "还有~~删除线 English~~文字"
+    );
+    assert_eq!(
+        run_text(
+            "这是一个脚注[^1]。\n\n[^1]: 脚注内容,English也在这里",
+            &Config::default()
+        ),
+        "这是一个脚注[^1]。\n\n[^1]: 脚注内容，English 也在这里"
+    );
+}
+
+#[test]
+fn test_soft_breaks() {
+    let config = Config::from_toml("[rules]\nno_line_break_between_full_width = true").unwrap();
+    assert_eq!(
+        run_text("中文段落被\n手动换行，\n“引号”\nEnglish\n文字", &config),
+        "中文段落被手动换行，“引号”\nEnglish\n文字"
+    );
+    // the lines are kept by default
+    assert_eq!(
+        run_text("中文段落被\n手动换行，", &Config::default()),
+        "中文段落被\n手动换行，"
+    );
+    let config = Config::from_toml(
+        "[rules]\nno_line_break_between_full_width = true\nreplace_line_break_with_space = true",
+    )
+    .unwrap();
+    assert_eq!(
+        run_text("中文段落被\n手动换行，\nEnglish\n文字`code`\n文字", &config),
+        "中文段落被手动换行，English 文字 `code` 文字"
+    );
+    let config = Config::from_toml(
+        "ignores = [\"文\\\\n中\"]\n[rules]\nno_line_break_between_full_width = true",
+    )
+    .unwrap();
+    assert_eq!(run_text("中文\n中文", &config), "中文\n中文");
+}
+
+#[test]
+fn test_prose_wrap() {
+    let config = Config::from_toml("prose_wrap = { width = 20 }").unwrap();
+    assert_eq!(
+        run_text(
+            "中文段落里面有很多文字，需要重新排版。使用`inline code`和[链接文字](https://example.com)测试，English words here。",
+            &config
+        ),
+        "中文段落里面有很多文\n字，需要重新排版。使\n用 `inline code` 和\n[链接文字](https://example.com)\n测试，English words\nhere。"
+    );
+    assert_eq!(
+        run_text("- 列表项里面的文字也需要被重新排版。", &config),
+        "* 列表项里面的文字也\n  需要被重新排版。"
+    );
+    // the first line counts the marker of the footnote
+    assert_eq!(
+        run_text("[^1]: 脚注里面有很多文字需要重新排版。", &config),
+        "[^1]: 脚注里面有很多\n文字需要重新排版。"
+    );
+    // no line starts with `，` or a list marker
+    assert_eq!(
+        run_text("一二三四五六七八九十，foo", &config),
+        "一二三四五六七八九\n十，foo"
+    );
+    assert_eq!(
+        run_text("一二三四五六七八 foo 1. bar", &config),
+        "一二三四五六七八\nfoo 1. bar"
+    );
+}
+
+#[test]
+fn test_templates() {
+    let config = Config::from_toml(r#"templates = ["hugo", "jinja"]"#).unwrap();
+    assert_eq!(
+        run_text(
+            r#"图片{{< figure src="a_b.png" title="标题,说明" >}}和{{% note %}}内容{{% /note %}}。"#,
+            &config
+        ),
+        r#"图片{{< figure src="a_b.png" title="标题,说明" >}}和{{% note %}}内容{{% /note %}}。"#
+    );
+    assert_eq!(
+        run_text(r#"{% include "foo.html" %}引用,{{ x,y }}文字"#, &config),
+        r#"{% include "foo.html" %}引用，{{ x,y }}文字"#
+    );
+    // not protected without the template
+    assert_eq!(
+        run_text(r#"{{< figure title="标题,说明" >}}"#, &Config::default()),
+        r#"{{\< figure title=“标题，说明” >}}"#
+    );
+
+    // the title of a container is formatted, and the lines are kept
+    let config = Config::from_toml(
+        r#"
+        templates = ["vuepress"]
+        prose_wrap = { width = 20 }
+        rules = { replace_line_break_with_space = true }
+        "#,
+    )
+    .unwrap();
+    assert_eq!(
+        run_text("::: tip 提示,注意\n内容,文字\n:::", &config),
+        "::: tip 提示，注意\n内容，文字\n:::"
+    );
+    assert_eq!(
+        run_text("文字{{ $t('a,b') }}", &config),
+        "文字{{ $t('a,b') }}"
+    );
+}
+
+#[test]
+fn test_mdx() {
+    let config = Config::from_toml("mdx = {}").unwrap();
+    assert_eq!(
+        run_text(
+            "import Tabs from '@theme/Tabs'\nexport const meta = {title: '标题'}\n\n文字",
+            &config
+        ),
+        "import Tabs from '@theme/Tabs'\nexport const meta = {title: '标题'}\n\n文字"
+    );
+    assert_eq!(
+        run_text(
+            "<Tabs>\n  <TabItem label=\"示例,一\" value=\"a,b\"\n    title='标题,二'>\n    中文内容,English\n  </TabItem>\n</Tabs>",
+            &config
+        ),
+        "<Tabs>\n  <TabItem label=\"示例，一\" value=\"a,b\"\n    title='标题，二'>\n    中文内容，English\n  </TabItem>\n</Tabs>"
+    );
+    assert_eq!(
+        run_text(
+            r#"文字<Badge text="新,的" title="新,的" />文字{props.name,x}结束,"#,
+            &config
+        ),
+        r#"文字<Badge text="新,的" title="新,的" />文字{props.name,x}结束，"#
+    );
+    assert_eq!(
+        run_text("<Note>\n<code>a,b</code> 注意,这里\n</Note>", &config),
+        "<Note>\n<code>a,b</code> 注意，这里\n</Note>"
+    );
+    // the escaped quotes are in the value, and formatted with it
+    assert_eq!(
+        run_text("<div title=\"说明\\\"a,b\\\",好\">\n</div>", &config),
+        "<div title=\"说明“a,b”，好\">\n</div>"
+    );
+}
+
+#[test]
+fn test_code_comments() {
+    let config = Config::from_toml(r#"code_comments = ["js", "python", "html", "bash"]"#).unwrap();
+    assert_eq!(
+        run_text(
+            "```js\n// 获取用户列表,然后渲染\nconst url = \"http://a.com,中文\"; // 注释,English\n/**\n * 块注释,中文\n */\n```",
+            &config
+        ),
+        "\n````js\n// 获取用户列表，然后渲染\nconst url = \"http://a.com,中文\"; // 注释，English\n/**\n * 块注释，中文\n */\n````"
+    );
+    assert_eq!(
+        run_text("```python\n# 中文,注释\ns = \"# 不是,注释\"\n```", &config),
+        "\n````python\n# 中文，注释\ns = \"# 不是,注释\"\n````"
+    );
+    assert_eq!(
+        run_text("```html\n<!-- 注释,中文 --><p>文字,中文</p>\n```", &config),
+        "\n````html\n<!-- 注释，中文 --><p>文字,中文</p>\n````"
+    );
+    // `#` in expansions or after other chars does not start a comment
+    assert_eq!(
+        run_text(
+            "```bash\necho ${#arr} \"中文,x\" a#b,中文 # 注释,中文\n```",
+            &config
+        ),
+        "\n````bash\necho ${#arr} \"中文,x\" a#b,中文 # 注释，中文\n````"
+    );
+    // other languages are not formatted
+    assert_eq!(
+        run_text("```go\n// 中文,注释\n```", &config),
+        "\n````go\n// 中文,注释\n````"
+    );
+}
+
+#[test]
+fn test_links() {
+    let text = r#"[链接,文字](https://a.com/x,y "标题,说明") ![图片,说明](a,b.png "图片,标题")"#;
+    assert_eq!(
+        run_text(text, &Config::default()),
+        r#"[链接，文字](https://a.com/x,y "标题,说明") ![图片，说明](a,b.png "图片,标题")"#
+    );
+    let config = Config::from_toml("links = { titles = true, image_alts = false }").unwrap();
+    assert_eq!(
+        run_text(text, &config),
+        r#"[链接，文字](https://a.com/x,y "标题，说明") ![图片,说明](a,b.png "图片，标题")"#
+    );
+
+    // the labels and the definitions are kept
+    assert_eq!(
+        run_text(
+            "[参考,文字][标签,一] [简写,标签] [折叠,标签][]\n\n[标签,一]: https://b.com \"定义,标题\"\n[简写,标签]: https://c.com\n[折叠,标签]: https://d.com",
+            &config
+        ),
+        "[参考，文字][标签,一] [简写,标签] [折叠,标签][]\n\n[标签,一]: https://b.com \"定义，标题\"\n[简写,标签]: https://c.com\n[折叠,标签]: https://d.com"
+    );
+    // the definitions in containers are kept in them
+    assert_eq!(
+        run_text("> [a]: https://a.com\n> 引用,文字\n\n[a]", &config),
+        "\n > \n > [a]: https://a.com\n > \n > 引用，文字\n\n[a]"
+    );
+    assert_eq!(
+        run_text(
+            "- [a]: https://a.com\n  文字\n- [b]: https://b.com\n- 列表",
+            &config
+        ),
+        "* [a]: https://a.com\n  文字\n* [b]: https://b.com\n* 列表"
+    );
+}
+
+#[test]
+fn test_anchors() {
+    let text = "## 使用Vue\n\n见[这里](#使用vue)\n\n[见此]: #%E4%BD%BF%E7%94%A8vue\n";
+    let config = Config::from_toml("anchors = {}").unwrap();
+    assert_eq!(
+        run_text(text, &config),
+        "## 使用 Vue\n\n见[这里](#使用-vue)\n\n[见此]: #使用-vue\n"
+    );
+
+    // the links are kept, and reported
+    let config = Config::from_toml(r#"anchors = { links = "report" }"#).unwrap();
+    let mut res = String::new();
+    let report = run_with_report(text, &config, &mut res).unwrap();
+    assert_eq!(
+        res,
+        "## 使用 Vue\n\n见[这里](#使用vue)\n\n[见此]: #%E4%BD%BF%E7%94%A8vue\n"
+    );
+    assert_eq!(
+        report.anchor_changes,
+        vec![AnchorChange {
+            old: "使用vue".into(),
+            new: "使用-vue".into()
+        }]
+    );
+    let positions: Vec<_> = report
+        .diagnostics
+        .iter()
+        .map(|x| x.position(text))
+        .collect();
+    assert_eq!(positions, vec![(3, 2), (5, 7)]);
+
+    // the links from other documents
+    let (res, _) = update_fragment_links(
+        "[链接](guide.md#使用vue) [其他](other.md#使用vue)",
+        &config,
+        |path| (path == "guide.md").then_some(report.anchor_changes.as_slice()),
+    );
+    assert_eq!(res, "[链接](guide.md#使用vue) [其他](other.md#使用vue)");
+    let config = Config::from_toml("anchors = {}").unwrap();
+    let (res, _) = update_fragment_links(
+        "[链接](guide.md#使用vue) [其他](other.md#使用vue)",
+        &config,
+        |path| (path == "guide.md").then_some(report.anchor_changes.as_slice()),
+    );
+    assert_eq!(res, "[链接](guide.md#使用-vue) [其他](other.md#使用vue)");
+
+    assert_eq!(
+        Slugger::GitHub.slug("使用 Vue 3.0，快速上手"),
+        "使用-vue-30快速上手"
+    );
+    assert_eq!(
+        Slugger::VuePress.slug("使用 Vue 3.0，快速上手"),
+        "使用-vue-3-0-快速上手"
+    );
+    assert_eq!(Slugger::VuePress.slug("1. 简介"), "_1-简介");
+}
+
+#[test]
+fn test_skip_elements() {
+    let text = "# 标题,文字\n\n> 引用,文字\\*转义\\*\n\n| 表头,一 | b |\n| --- | --- |\n| 单元格,文字 | `code` |\n\n正文,文字[链接,文字](https://a.com)\n\n- 列表,文字";
+    let config =
+        Config::from_toml(r#"skip_elements = ["table", "blockquote", "heading", "link_text"]"#)
+            .unwrap();
+    assert_eq!(
+        run_text(text, &config),
+        "# 标题,文字\n\n> 引用,文字\\*转义\\*\n\n| 表头,一 | b |\n| --- | --- |\n| 单元格,文字 | `code` |\n\n正文，文字[链接,文字](https://a.com)\n\n* 列表，文字"
+    );
+
+    // the skipped blocks are written byte by byte, also inside containers
+    let text = "- 列表,一\n\n  > 引用,文字\n  >   第二行,文字\n\n  文字,二\n\n-   列表,文字\n    1. 嵌套,列表\n\n[^1]: 脚注,文字";
+    let config = Config::from_toml(r#"skip_elements = ["blockquote", "footnote"]"#).unwrap();
+    assert_eq!(
+        run_text(text, &config),
+        "* 列表，一\n  \n  > 引用,文字\n  >   第二行,文字\n  \n  文字，二\n\n* 列表，文字\n  \n  1. 嵌套，列表\n\n[^1]: 脚注,文字"
+    );
+    let config = Config::from_toml(r#"skip_elements = ["list", "footnote"]"#).unwrap();
+    assert_eq!(run_text(text, &config), text);
+}
+
+#[test]
+fn test_ignores_after_cjk() {
+    // the ranges are byte offsets, the chars before take three bytes
+    let config = Config::from_toml(r#"ignores = ["中文,中文"]"#).unwrap();
+    assert_eq!(run_text("说明：中文,中文", &config), "说明：中文,中文");
+}
@@ -0,0 +1,379 @@
+use zhlint::{
+    config::{find_config_files, Config, ConfigLayers, ConfigSource, JsConfig, ZhScript},
+    run, run_with_layers, run_with_report, Error,
+};
+
+#[test]
+fn test_unknown_keys() {
+    let err = Config::from_toml("[rules]\nspace_outside_cod = false\n").unwrap_err();
+    assert_eq!((err.line, err.column), (Some(2), Some(1)));
+    assert_eq!(err.message, "unknown field `space_outside_cod`");
+    assert_eq!(err.suggestion.as_deref(), Some("space_outside_code"));
+
+    let err = Config::from_toml("ignore = []\n").unwrap_err();
+    assert_eq!(err.suggestion.as_deref(), Some("ignores"));
+
+    let err = Config::from_toml("[rules]\nfoo = 1\n").unwrap_err();
+    assert_eq!(err.suggestion, None);
+    assert!(err.message.contains("expected one of"));
+}
+
+#[test]
+fn test_valid_config() {
+    let config =
+        Config::from_toml("ignores = ['foo']\n[rules]\nspace_outside_code = false\n").unwrap();
+    assert_eq!(config.ignores, vec!["foo"]);
+    assert_eq!(config.rules.space_outside_code, Some(false));
+    assert!(config.rules.no_space_inside_wrapper);
+}
+
+#[test]
+fn test_preset() {
+    let config =
+        Config::from_toml("preset = 'empty'\n[rules]\nspace_outside_code = true\n").unwrap();
+    assert_eq!(config.rules.space_outside_code, Some(true));
+    assert_eq!(config.rules.space_between_mixed_width_letters, None);
+    assert!(!config.rules.no_space_inside_wrapper);
+
+    let err = Config::from_toml("preset = 'defualt'\n").unwrap_err();
+    assert_eq!(err.message, "unknown preset `defualt`");
+    assert_eq!(err.suggestion.as_deref(), Some("default"));
+}
+
+#[test]
+fn test_annotated_config() {
+    let mut layers = ConfigLayers::new();
+    layers
+        .push_toml(
+            ConfigSource::File(".zhlintrc.toml".into()),
+            "[rules]\nspace_outside_code = false\n",
+        )
+        .unwrap();
+    let res = layers.to_annotated_toml().unwrap();
+    assert!(res.contains("space_outside_code = false  # .zhlintrc.toml\n"));
+    assert!(res.contains("space_outside_half_bracket = true  # preset `default`\n"));
+    assert!(res.contains("\n[rules]\n"));
+}
+
+#[test]
+fn test_inherited_config() {
+    let root = std::env::temp_dir().join("zhlint_test_inherited_config");
+    let dir = root.join("docs").join("guide");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        root.join(".zhlintrc.toml"),
+        "[rules]\nspace_outside_code = false\ntrim_space = true\n",
+    )
+    .unwrap();
+    std::fs::write(
+        root.join("docs").join(".zhlintrc"),
+        r#"{"rules": {"trimSpace": false}}"#,
+    )
+    .unwrap();
+
+    let files = find_config_files(dir.join("index.md"));
+    let files: Vec<_> = files.iter().filter(|x| x.starts_with(&root)).collect();
+    assert_eq!(files.len(), 2);
+    let mut layers = ConfigLayers::new();
+    for file in files {
+        layers.push_file(file).unwrap();
+    }
+    let config = layers.resolve().unwrap();
+    assert_eq!(config.rules.space_outside_code, Some(false));
+    assert!(!config.rules.trim_space);
+    let res = layers.to_annotated_toml().unwrap();
+    assert!(res.contains(&format!(
+        "trim_space = false  # {}\n",
+        root.canonicalize()
+            .unwrap()
+            .join("docs")
+            .join(".zhlintrc")
+            .display()
+    )));
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_json_schema() {
+    let schema: serde_json::Value = serde_json::from_str(&Config::json_schema()).unwrap();
+    let rules = &schema["definitions"]["Rules"];
+    assert_eq!(rules["additionalProperties"], false);
+    assert_eq!(rules["properties"]["space_outside_code"]["default"], true);
+    assert!(rules["properties"]["space_outside_code"]["description"]
+        .as_str()
+        .unwrap()
+        .contains("one space"));
+    assert_eq!(
+        schema["definitions"]["ZhScript"]["enum"],
+        serde_json::json!(["simplified", "traditional"])
+    );
+
+    // the lowercase values in the docs are valid, and the old names still load
+    let config = r#"
+        skip_elements = ["table", "blockquote", "heading", "link_text"]
+        templates = ["hugo", "vuepress"]
+        anchors = { slugger = "github", links = "report" }
+        [rules]
+        unified_punctuation = "traditional"
+        fix_emphasis = "html"
+        consistent_list_item_ending = "none"
+    "#;
+    let value = serde_json::to_value(toml::from_str::<toml::Table>(config).unwrap()).unwrap();
+    let validator = jsonschema::JSONSchema::compile(&schema).unwrap();
+    assert!(validator.is_valid(&value));
+    let config = Config::from_toml(config).unwrap();
+    assert!(serde_json::to_string(&config)
+        .unwrap()
+        .contains(r#"["table","blockquote","heading","link_text"]"#));
+    assert!(Config::from_toml(r#"skip_elements = ["LinkText"]"#).is_ok());
+}
+
+#[test]
+fn test_js_config() {
+    let js_config = JsConfig::parse(
+        r#"{
+            "preset": "default",
+            "rules": {
+                "spaceOutsideCode": false,
+                "skipZhUnits": "年月",
+                "unifiedPunctuation": "traditional",
+                "noSpaceInsideHyperMark": true,
+                "spaceBetweenMixedwidthContent": null,
+                "noSpaceInsideQuotation": null,
+                "skipPureWestern": true
+            },
+            "ignores": ["foo", { "prefix": "a", "textStart": "(", "textEnd": ")" }]
+        }"#,
+    )
+    .unwrap();
+    assert_eq!(
+        js_config.warnings,
+        vec!["`skipPureWestern` is not supported, ignored"]
+    );
+
+    let mut layers = ConfigLayers::new();
+    layers
+        .push_js(
+            ConfigSource::File(".zhlintrc".into()),
+            r#"{ "rules": { "spaceOutsideCode": false } }"#,
+        )
+        .unwrap();
+    let config = layers.resolve().unwrap();
+    assert_eq!(config.rules.space_outside_code, Some(false));
+    assert_eq!(config.rules.space_between_mixed_width_letters, Some(true));
+
+    let (toml, dropped) = js_config.to_toml();
+    assert!(toml.contains("unified_punctuation = \"traditional\"\n"));
+    assert_eq!(dropped, vec!["rules.space_between_mixed_width_letters"]);
+    let config = Config::from_toml(&toml).unwrap();
+    assert_eq!(config.rules.space_outside_code, Some(false));
+    assert_eq!(config.rules.skip_zh_units, vec!['年', '月']);
+    assert!(matches!(
+        config.rules.unified_punctuation,
+        Some(ZhScript::Traditional)
+    ));
+    // `null` is `false`, but not the default preset
+    assert!(!config.rules.no_space_inside_quote);
+    assert_eq!(config.ignores, vec![r"foo", r"a(?<ignore>\((?s:.*?)\))"]);
+
+    let err = JsConfig::parse(r#"{ "rules": { "spaceOutsideCod": false } }"#).unwrap_err();
+    assert_eq!(err.suggestion.as_deref(), Some("space_outside_code"));
+}
+
+#[test]
+fn test_command_line_overrides() {
+    let mut layers = ConfigLayers::new();
+    layers
+        .push_toml(
+            ConfigSource::File(".zhlintrc.toml".into()),
+            "ignores = ['foo']\n[rules]\nspace_outside_code = false\n",
+        )
+        .unwrap();
+    layers
+        .push_args(
+            &[
+                "space_outside_code=true".to_string(),
+                "rules.half_width_punctuation=（）".to_string(),
+                "space_outside_half_quote=null".to_string(),
+                "skip_zh_units=['年']".to_string(),
+            ],
+            &["bar".to_string()],
+        )
+        .unwrap();
+    let config = layers.resolve().unwrap();
+    assert_eq!(config.rules.space_outside_code, Some(true));
+    assert_eq!(config.rules.half_width_punctuation, "（）");
+    assert_eq!(config.rules.space_outside_half_quote, None);
+    assert_eq!(config.rules.skip_zh_units, vec!['年']);
+    assert_eq!(config.ignores, vec!["foo", "bar"]);
+    assert_eq!(
+        layers.sources()["rules.space_outside_code"],
+        ConfigSource::CommandLine
+    );
+
+    let err = ConfigLayers::new()
+        .push_args(&["space_outside_cod=true".to_string()], &[])
+        .unwrap_err();
+    assert_eq!(err.path.as_deref(), Some("--rule space_outside_cod=true"));
+    assert_eq!(err.suggestion.as_deref(), Some("space_outside_code"));
+    assert!(ConfigLayers::new()
+        .push_args(&["space_outside_code".to_string()], &[])
+        .is_err());
+}
+
+#[test]
+fn test_front_matter_config() {
+    let run_text = |text: &str| {
+        let mut res = String::new();
+        run(text, &Config::default(), &mut res).map(|_| res)
+    };
+
+    assert_eq!(
+        run_text("---\nzhlint: { rules: { space_outside_code: false } }\n---\n中文 `code` 中文\n")
+            .unwrap(),
+        "---\nzhlint: { rules: { space_outside_code: false } }\n---\n中文`code`中文"
+    );
+    assert_eq!(
+        run_text("+++\ntitle = '标题'\n[zhlint.rules]\nspace_outside_code = false\n+++\n中文 `code` 中文\n")
+            .unwrap(),
+        "+++\ntitle = '标题'\n[zhlint.rules]\nspace_outside_code = false\n+++\n中文`code`中文"
+    );
+    assert_eq!(
+        run_text("---\ntitle: 标题\n---\n中文`code`中文\n").unwrap(),
+        "---\ntitle: 标题\n---\n中文 `code` 中文"
+    );
+
+    let err =
+        run_text("---\nzhlint: { rules: { space_outside_cod: false } }\n---\n中文\n").unwrap_err();
+    let Error::Config(err) = err else {
+        panic!("expected a config error");
+    };
+    assert_eq!(err.suggestion.as_deref(), Some("space_outside_code"));
+
+    // the front matter which does not parse is kept, if it has no `zhlint` key
+    assert_eq!(
+        run_text("---\ntitle: a: b\n---\n\n中文,English\n").unwrap(),
+        "---\ntitle: a: b\n---\n中文，English"
+    );
+    assert_eq!(
+        run_text("+++\ntitle = \n+++\n中文,English\n").unwrap(),
+        "+++\ntitle = \n+++\n中文，English"
+    );
+    assert!(run_text("---\nzhlint: a: b\n---\n中文\n").is_err());
+    // the ending delimiter is a whole line
+    assert_eq!(
+        run_text("---\ntitle: a---\ndescription: b\n---\n中文`code`\n").unwrap(),
+        "---\ntitle: a---\ndescription: b\n---\n中文 `code`"
+    );
+}
+
+#[test]
+fn test_invalid_ignores() {
+    let err = ConfigLayers::new()
+        .push_args(&[], &["(".to_string()])
+        .unwrap_err();
+    assert_eq!(err.path.as_deref(), Some("--ignore ("));
+    assert_eq!(err.message, "invalid regex `(` in ignores: unclosed group");
+
+    let err = Config::from_toml(r#"ignores = ["("]"#).unwrap_err();
+    assert_eq!(err.message, "invalid regex `(` in ignores: unclosed group");
+
+    let mut res = String::new();
+    let err = run(
+        "---\nzhlint:\n  ignores: ['(']\n---\n文字",
+        &Config::default(),
+        &mut res,
+    )
+    .unwrap_err();
+    assert!(matches!(err, Error::Config(e) if e.path.as_deref() == Some("front matter")));
+
+    // the configs built in code and the ignore comments are also checked
+    let config = Config {
+        ignores: vec!["(".to_string()],
+        ..Config::default()
+    };
+    assert!(run("文字", &config, &mut res).is_err());
+    let err = run(
+        "<!-- zhlint ignore: ( -->\n\n文字",
+        &Config::default(),
+        &mut res,
+    )
+    .unwrap_err();
+    assert!(matches!(err, Error::Config(e) if e.path.as_deref() == Some("ignore comment")));
+}
+
+#[test]
+fn test_front_matter_layers() {
+    let mut layers = ConfigLayers::new();
+    layers
+        .push_toml(
+            ConfigSource::File(".zhlintrc.toml".into()),
+            "[rules]\nspace_outside_code = false\n",
+        )
+        .unwrap();
+    layers
+        .push_args(
+            &["space_after_half_width_punctuation=false".to_string()],
+            &[],
+        )
+        .unwrap();
+    let text = "---\nzhlint:\n  rules:\n    space_outside_code: true\n    \
+                space_after_half_width_punctuation: true\n---\n中文`code`中文,English";
+
+    // the front matter overrides the files, but not the command line
+    let mut res = String::new();
+    run_with_layers(text, &layers, &mut res).unwrap();
+    assert!(res.ends_with("\n中文 `code` 中文，English"));
+
+    // the printed config formats the same
+    let printed = layers
+        .with_front_matter(text)
+        .unwrap()
+        .to_annotated_toml()
+        .unwrap();
+    assert!(printed.contains("space_outside_code = true  # front matter\n"));
+    assert!(printed.contains("space_after_half_width_punctuation = false  # command line\n"));
+    let config = Config::from_toml(&printed).unwrap();
+    let mut printed_res = String::new();
+    run_with_report(
+        &text[text.find("\n---\n").unwrap() + 5..],
+        &config,
+        &mut printed_res,
+    )
+    .unwrap();
+    assert!(res.ends_with(&printed_res));
+
+    // the preset can not be changed by a document
+    let text = "---\nzhlint:\n  preset: empty\n---\n中文";
+    let err = layers.with_front_matter(text).unwrap_err();
+    assert_eq!(err.path.as_deref(), Some("front matter"));
+    assert!(run(text, &Config::default(), &mut res).is_err());
+}
+
+#[test]
+fn test_config_file_errors() {
+    let dir = std::env::temp_dir().join("zhlint_test_config_file_errors");
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let path = dir.join(".zhlintrc.toml");
+    std::fs::write(&path, "preset = 'defualt'\n").unwrap();
+    let err = Config::from_file(&path).unwrap_err();
+    assert_eq!(err.path, Some(path.display().to_string()));
+    assert_eq!((err.line, err.column), (Some(1), Some(10)));
+    assert_eq!(err.message, "unknown preset `defualt`");
+
+    std::fs::write(&path, "ignores = [\n  'foo',\n  '(',\n]\n").unwrap();
+    let err = Config::from_file(&path).unwrap_err();
+    assert_eq!(err.path, Some(path.display().to_string()));
+    assert_eq!((err.line, err.column), (Some(3), Some(3)));
+    assert_eq!(err.message, "invalid regex `(` in ignores: unclosed group");
+
+    let path = dir.join(".zhlintrc");
+    std::fs::write(&path, r#"{ "preset": "defualt" }"#).unwrap();
+    let err = Config::from_file(&path).unwrap_err();
+    assert_eq!(err.path, Some(path.display().to_string()));
+    assert_eq!(err.suggestion.as_deref(), Some("default"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
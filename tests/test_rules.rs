@@ -1,6 +1,6 @@
 use zhlint::{
-    config::{Config, Rules, ZhScript},
-    run,
+    config::{Config, Element, EmphasisFix, ListItemEnding, Rules, ZhScript},
+    run, run_with_report,
 };
 
 fn run_text(text: &str, config: &Config) -> String {
@@ -26,17 +26,64 @@ fn test_trim_space() {
     assert_eq!(run_text(r#" (bar) "#, &config), r#"(bar)"#);
 }
 
-// #[test]
-// fn test_hyper_mark() {
-//     let config = Config {
-//         no_space_inside_wrapper: true,
-//         ..Default::default()
-//     };
-//     assert_eq!(run_text(r#"x ** yyy ** z"#, &config), r#"x **yyy** z"#);
-//     assert_eq!(run_text(r#"x _** yyy ** _ z"#, &config), r#"x _**yyy**_ z"#);
-//     assert_eq!(run_text(r#"x _ ** yyy **_ z"#, &config), r#"x _**yyy**_ z"#);
-//     assert_eq!(run_text(r#"_ ** yyy **_"#, &config), r#"_**yyy**_"#);
-// }
+#[test]
+fn test_trim_space_of_blocks() {
+    let mut config = Config::empty();
+    config.rules.trim_space = true;
+
+    assert_eq!(run_text("　文字\u{a0}", &config), "文字");
+    assert_eq!(run_text("　**文字**　", &config), "**文字**");
+    assert_eq!(run_text("# 　标题　", &config), "# 标题");
+    assert_eq!(
+        run_text("- 　列表　\n- [ ] 　任务", &config),
+        "* 列表\n* [ ] 任务"
+    );
+    assert_eq!(
+        run_text("| 　a　 |\n| - |\n| 　中文 |", &config),
+        "| a    |\n| ---- |\n| 中文 |"
+    );
+    config.ignores = vec!["文字\u{a0}".to_string()];
+    assert_eq!(run_text("　文字\u{a0}", &config), "文字\u{a0}");
+    assert_eq!(run_text("# 　标题　", &config), "# 标题");
+}
+
+#[test]
+fn test_hyper_mark() {
+    let mut config = Config::empty();
+    config.rules.no_space_inside_wrapper = true;
+
+    // arithmetic and prose are not emphasis, unless there is emphasis
+    assert_eq!(run_text(r#"x ** yyy ** z"#, &config), r#"x ** yyy ** z"#);
+    assert_eq!(run_text(r#"2 ** 3 ** 2"#, &config), r#"2 ** 3 ** 2"#);
+    assert_eq!(
+        run_text(r#"a ** b 和 c ** d"#, &config),
+        r#"a ** b 和 c ** d"#
+    );
+    assert_eq!(run_text(r#"*x* ** yyy ** z"#, &config), r#"*x* **yyy** z"#);
+    assert_eq!(run_text(r#"x [ yyy ](url)z"#, &config), r#"x [yyy](url) z"#);
+    assert_eq!(
+        run_text(r#"x *[yyy ](url)* z"#, &config),
+        r#"x *[yyy](url)* z"#
+    );
+    assert_eq!(run_text(r#"2 * 3 * 4"#, &config), r#"2 * 3 * 4"#);
+
+    // the spaces outside are decided by the other rules
+    let mut config = Config::default();
+    config.rules.no_space_inside_wrapper = true;
+    assert_eq!(run_text(r#"2 ** 3 ** 2"#, &config), r#"2 ** 3 ** 2"#);
+    assert_eq!(
+        run_text(r#"a ** b 和 c ** d"#, &config),
+        r#"a ** b 和 c ** d"#
+    );
+    assert_eq!(
+        run_text(r#"文字** foo **文字"#, &config),
+        r#"文字 **foo** 文字"#
+    );
+    assert_eq!(
+        run_text(r#"文字** 文字 **文字"#, &config),
+        r#"文字**文字**文字"#
+    );
+}
 
 #[test]
 fn test_hyper_code() {
@@ -50,6 +97,93 @@ fn test_hyper_code() {
     assert_eq!(run_text(r#"xxx ` foo`xxx"#, &config), r#"xxx ` foo` xxx"#);
 }
 
+#[test]
+fn test_hyper_html() {
+    let mut config = Config::empty();
+    config.rules.space_outside_code = Some(true);
+    config.rules.code_like_html_tags = vec!["kbd".to_string()];
+
+    assert_eq!(
+        run_text(r#"按<kbd>Ctrl</kbd>键"#, &config),
+        r#"按 <kbd>Ctrl</kbd> 键"#
+    );
+    assert_eq!(
+        run_text(r#"按<kbd>Ctrl</kbd> 键"#, &config),
+        r#"按 <kbd>Ctrl</kbd> 键"#
+    );
+    // not code-like
+    assert_eq!(
+        run_text(r#"第一行<br>第二行<span>文字</span>"#, &config),
+        r#"第一行<br>第二行<span>文字</span>"#
+    );
+
+    config.rules.space_outside_code = Some(false);
+    assert_eq!(
+        run_text(r#"按 <kbd>Ctrl</kbd> 键"#, &config),
+        r#"按<kbd>Ctrl</kbd>键"#
+    );
+
+    // the content of code-like elements is kept, other tags are seen through
+    let mut config = Config::default();
+    config.rules.code_like_html_tags = vec!["kbd".to_string()];
+    assert_eq!(
+        run_text(r#"按<kbd>Ctrl,C</kbd>复制"#, &config),
+        r#"按 <kbd>Ctrl,C</kbd> 复制"#
+    );
+    assert_eq!(
+        run_text(r#"中文<span>English文字</span>中文"#, &config),
+        r#"中文 <span>English 文字</span>中文"#
+    );
+    assert_eq!(
+        run_text(r#"<span>文字 ,文字</span>"#, &config),
+        r#"<span>文字，文字</span>"#
+    );
+}
+
+#[test]
+fn test_hyper_math() {
+    let mut config = Config::empty();
+    config.extensions.math = true;
+    config.rules.space_outside_math = Some(true);
+    config.rules.space_after_half_width_punctuation = Some(true);
+
+    assert_eq!(
+        run_text(r#"复杂度$O(n)$的算法"#, &config),
+        r#"复杂度 $O(n)$ 的算法"#
+    );
+    // the math is kept as it is
+    assert_eq!(
+        run_text(r#"其中$f(x,y)=x*y*2$成立"#, &config),
+        r#"其中 $f(x,y)=x*y*2$ 成立"#
+    );
+    assert_eq!(
+        run_text(r#"公式$$\sum_{i=1}^n i,$$如下"#, &config),
+        r#"公式 $$\sum_{i=1}^n i,$$ 如下"#
+    );
+    // not math
+    assert_eq!(run_text(r#"价格$5和$10"#, &config), r#"价格$5和$10"#);
+    assert_eq!(run_text(r#"中文`$x$`"#, &config), r#"中文`$x$`"#);
+
+    config.rules.space_outside_math = Some(false);
+    assert_eq!(
+        run_text(r#"复杂度 $O(n)$ 的算法"#, &config),
+        r#"复杂度$O(n)$的算法"#
+    );
+
+    config.rules.space_outside_math = Some(true);
+    // other raw text is not math
+    config.skip_elements = vec![Element::LinkText];
+    assert_eq!(
+        run_text(r#"价格[$5](url)元"#, &config),
+        r#"价格[$5](url)元"#
+    );
+    config.extensions.math = false;
+    assert_eq!(
+        run_text(r#"复杂度$O(n)$的算法"#, &config),
+        r#"复杂度$O(n)$的算法"#
+    );
+}
+
 #[test]
 fn test_punctuation_width() {
     let mut config = Config::empty();
@@ -65,6 +199,8 @@ fn test_punctuation_width() {
         r#"“你‘好’”，再见。"#
     );
     assert_eq!(run_text(r#"what's up"#, &config), r#"what's up"#);
+    // inline markups are seen through
+    assert_eq!(run_text(r#"**foo**, bar"#, &config), r#"**foo**, bar"#);
 }
 
 #[test]
@@ -284,3 +420,188 @@ fn test_space_bracket() {
         r#"foo（ bar ）baz"#
     );
 }
+
+#[test]
+fn test_fix_emphasis() {
+    let mut config = Config::empty();
+    config.rules.fix_emphasis = Some(EmphasisFix::Space);
+    config.rules.space_between_mixed_width_letters = Some(true);
+
+    assert_eq!(run_text(r#"**“重要”**之后"#, &config), r#"**“重要”** 之后"#);
+    assert_eq!(
+        run_text(r#"前面**注意：**文字"#, &config),
+        r#"前面**注意：** 文字"#
+    );
+    assert_eq!(
+        run_text(r#"前面*注意：*文字"#, &config),
+        r#"前面*注意：* 文字"#
+    );
+    assert_eq!(
+        run_text(r#"前面__注意__文字"#, &config),
+        r#"前面 **注意** 文字"#
+    );
+    assert_eq!(run_text(r#"**普通**文字"#, &config), r#"**普通**文字"#);
+    assert_eq!(
+        run_text(r#"\**注意：**文字"#, &config),
+        r#"\*\*注意：\*\*文字"#
+    );
+
+    config.rules.space_between_mixed_width_letters = Some(false);
+    assert_eq!(run_text(r#"注意：**文字"#, &config), r#"注意：\*\*文字"#);
+    assert_eq!(
+        run_text(r#"**注意：**文字"#, &config),
+        r#"\*\*注意：\*\*文字"#
+    );
+
+    config.rules.fix_emphasis = Some(EmphasisFix::Html);
+    assert_eq!(
+        run_text(r#"前面**注意：**文字"#, &config),
+        r#"前面<strong>注意：</strong>文字"#
+    );
+    assert_eq!(
+        run_text(r#"前面*注意：*文字"#, &config),
+        r#"前面<em>注意：</em>文字"#
+    );
+}
+
+#[test]
+fn test_inline_markups() {
+    let mut config = Config::empty();
+    config.rules.space_between_mixed_width_letters = Some(true);
+
+    assert_eq!(
+        run_text(r#"[Vue](https://vuejs.org)文档"#, &config),
+        r#"[Vue](https://vuejs.org) 文档"#
+    );
+    assert_eq!(run_text(r#"文档*foo*文字"#, &config), r#"文档 *foo* 文字"#);
+    assert_eq!(run_text(r#"**文档**foo"#, &config), r#"**文档** foo"#);
+
+    let mut config = Config::empty();
+    config.rules.no_space_between_full_width_letters = true;
+    assert_eq!(
+        run_text(r#"文档 *文字* 文字"#, &config),
+        r#"文档*文字*文字"#
+    );
+
+    let mut config = Config::empty();
+    config.rules.no_space_outside_full_quote = true;
+    config.rules.no_space_after_full_width_punctuation = true;
+    assert_eq!(
+        run_text(r#"文字 “*引用*” 文字"#, &config),
+        r#"文字“*引用*”文字"#
+    );
+    // the spaces are kept, or the emphasis is broken
+    assert_eq!(
+        run_text(r#"**“重要”** 之后，**注意：** 文字"#, &config),
+        r#"**“重要”** 之后，**注意：** 文字"#
+    );
+}
+
+#[test]
+fn test_heading_punctuation() {
+    let mut config = Config::empty();
+    config.rules.no_end_punctuation_in_heading = true;
+
+    assert_eq!(run_text("# 标题。", &config), "# 标题");
+    assert_eq!(run_text("## 安装：", &config), "## 安装");
+    assert_eq!(run_text("## Install.", &config), "## Install");
+    assert_eq!(run_text("## **注意**：", &config), "## **注意**");
+    assert_eq!(run_text("## **注意：**", &config), "## **注意**");
+    assert_eq!(run_text("## 为什么？", &config), "## 为什么？");
+    assert_eq!(run_text("## 。", &config), "## 。");
+    // the text is not a heading
+    assert_eq!(run_text("文字。", &config), "文字。");
+
+    config.ignores = vec!["安装：".to_string()];
+    assert_eq!(run_text("## 安装：", &config), "## 安装：");
+    assert_eq!(run_text("## 配置：", &config), "## 配置");
+
+    // off by default
+    assert_eq!(run_text("## 安装：", &Config::default()), "## 安装：");
+}
+
+#[test]
+fn test_heading_numbering() {
+    let mut config = Config::empty();
+    config.rules.consistent_heading_numbering = true;
+
+    let text = "# 标题\n\n## 一、安装\n\n### 1. 下载\n\n## 2. 配置\n\n## （三）使用\n\n## 附录";
+    let mut res = String::new();
+    let report = run_with_report(text, &config, &mut res).unwrap();
+    assert_eq!(res, text);
+    let diagnostics: Vec<_> = report
+        .diagnostics
+        .iter()
+        .map(|x| (x.position(text), x.message.as_str()))
+        .collect();
+    assert_eq!(
+        diagnostics,
+        vec![
+            (
+                (7, 1),
+                "the numbering `1.` is mixed with `一、` in the headings of level 2"
+            ),
+            (
+                (9, 1),
+                "the numbering `（一）` is mixed with `一、` in the headings of level 2"
+            ),
+        ]
+    );
+
+    // off by default
+    let mut res = String::new();
+    let report = run_with_report(text, &Config::default(), &mut res).unwrap();
+    assert!(report.diagnostics.is_empty());
+}
+
+#[test]
+fn test_list_item_ending() {
+    let mut config = Config::empty();
+    config.rules.consistent_list_item_ending = Some(ListItemEnding::Majority);
+
+    let text = "- 第一项；\n- 第二项；\n- 第三项\n- 使用 `npm`\n- English\n\n* 甲。\n* 乙";
+    let mut res = String::new();
+    let report = run_with_report(text, &config, &mut res).unwrap();
+    assert_eq!(res, text.replace('-', "*"));
+    let diagnostics: Vec<_> = report
+        .diagnostics
+        .iter()
+        .map(|x| (x.position(text), x.message.as_str()))
+        .collect();
+    assert_eq!(
+        diagnostics,
+        vec![
+            (
+                (3, 1),
+                "the list item ends with no punctuation, but `；` is expected"
+            ),
+            (
+                (8, 1),
+                "the list item ends with no punctuation, but `。` is expected"
+            ),
+        ]
+    );
+
+    config.rules.fix_list_item_ending = true;
+    assert_eq!(
+        run_text(text, &config),
+        "* 第一项；\n* 第二项；\n* 第三项；\n* 使用 `npm`\n* English\n\n* 甲。\n* 乙。"
+    );
+    config.rules.consistent_list_item_ending = Some(ListItemEnding::Nothing);
+    assert_eq!(
+        run_text("- 第一项；\n- 第二项。\n  - 嵌套；\n  - 嵌套", &config),
+        "* 第一项\n* 第二项\n  * 嵌套\n  * 嵌套"
+    );
+
+    // the ignored endings are kept
+    config.ignores = vec!["第一项；".to_string()];
+    assert_eq!(
+        run_text("- 第一项；\n- 第二项；", &config),
+        "* 第一项；\n* 第二项"
+    );
+
+    // off by default
+    let mut res = String::new();
+    let report = run_with_report(text, &Config::default(), &mut res).unwrap();
+    assert!(report.diagnostics.is_empty());
+}